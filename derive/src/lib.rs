@@ -0,0 +1,310 @@
+//! `#[derive(HtipTlv)]`, which lays out a tlv of rust-htip as a struct. See
+//! `rust_htip::typed::HtipTlv` for the attributes.
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::meta::ParseNestedMeta;
+use syn::{parse_macro_input, Data, DeriveInput, Error, Fields, LitInt, LitStr, Result};
+
+/// The OUI of HTIP tlvs
+const TTC_OUI: [u8; 3] = [0xe0, 0x27, 0x1a];
+
+#[proc_macro_derive(HtipTlv, attributes(htip))]
+pub fn derive_htip_tlv(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// The `#[htip(..)]` attributes of a struct
+struct TlvAttrs {
+    name: String,
+    tlv_type: u8,
+    prefix: Vec<u8>,
+}
+
+fn tlv_attrs(input: &DeriveInput) -> Result<TlvAttrs> {
+    let mut name = input.ident.to_string();
+    let mut tlv_type = 127;
+    let mut oui = TTC_OUI.to_vec();
+    let mut key = None;
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("htip"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("key") {
+                key = Some(hex(&meta.value()?.parse()?)?);
+            } else if meta.path.is_ident("oui") {
+                oui = hex(&meta.value()?.parse()?)?;
+            } else if meta.path.is_ident("tlv_type") {
+                let lit: LitInt = meta.value()?.parse()?;
+                tlv_type = lit.base10_parse()?;
+                if tlv_type > 127 {
+                    return Err(Error::new_spanned(lit, "tlv_type must be from 0 to 127"));
+                }
+            } else if meta.path.is_ident("name") {
+                name = meta.value()?.parse::<LitStr>()?.value();
+            } else {
+                return Err(meta.error("expected key, oui, tlv_type or name"));
+            }
+            Ok(())
+        })?;
+    }
+    let key = key.ok_or_else(|| {
+        Error::new_spanned(&input.ident, "missing #[htip(key = \"..\")] attribute")
+    })?;
+    Ok(TlvAttrs {
+        name,
+        tlv_type,
+        prefix: [oui, key].concat(),
+    })
+}
+
+/// Bytes from hexadecimal digits, optionally separated by `:` or spaces
+fn hex(lit: &LitStr) -> Result<Vec<u8>> {
+    let digits = lit
+        .value()
+        .chars()
+        .filter(|c| *c != ':' && !c.is_whitespace())
+        .collect::<Vec<_>>();
+    if digits.len() % 2 != 0 {
+        return Err(Error::new_spanned(lit, "odd number of hexadecimal digits"));
+    }
+    digits
+        .chunks(2)
+        .map(|pair| {
+            u8::from_str_radix(&pair.iter().collect::<String>(), 16)
+                .map_err(|_| Error::new_spanned(lit, "invalid hexadecimal digits"))
+        })
+        .collect()
+}
+
+/// The `key = N` argument of a layout, if any
+fn argument(meta: &ParseNestedMeta, key: &str, min: usize, max: usize) -> Result<Option<usize>> {
+    if !meta.input.peek(syn::token::Paren) {
+        return Ok(None);
+    }
+    let mut value = None;
+    meta.parse_nested_meta(|argument| {
+        if !argument.path.is_ident(key) {
+            return Err(argument.error(format!("expected {}", key)));
+        }
+        let lit: LitInt = argument.value()?.parse()?;
+        let number = lit.base10_parse()?;
+        if number < min || number > max {
+            return Err(Error::new_spanned(
+                lit,
+                format!("{} must be from {} to {}", key, min, max),
+            ));
+        }
+        value = Some(number);
+        Ok(())
+    })?;
+    Ok(value)
+}
+
+/// Check the ranges (e.g. `a-z`) of a charset
+fn check_charset(lit: &LitStr) -> Result<()> {
+    let chars = lit.value().chars().collect::<Vec<_>>();
+    match chars
+        .windows(3)
+        .find(|window| window[1] == '-' && window[0] > window[2])
+    {
+        Some(range) => Err(Error::new_spanned(
+            lit,
+            format!("invalid range {}-{}", range[0], range[2]),
+        )),
+        None => Ok(()),
+    }
+}
+
+/// The kind of values of the field types rust-htip implements, if `ty` is
+/// one of them
+fn value_kind(ty: &syn::Type) -> Option<&'static str> {
+    let segment = match ty {
+        syn::Type::Path(path) => path.path.segments.last()?,
+        _ => return None,
+    };
+    match segment.ident.to_string().as_str() {
+        "String" => Some("text"),
+        "u8" | "u16" | "u32" | "u64" => Some("number"),
+        "Vec" => Some("MAC list"),
+        _ => None,
+    }
+}
+
+/// The layout & charset of a field
+fn field_attrs(field: &syn::Field) -> Result<(TokenStream2, Option<String>)> {
+    let mut layout = None;
+    let mut kind = "";
+    let mut charset = None;
+    for attr in field
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("htip"))
+    {
+        attr.parse_nested_meta(|meta| {
+            let tokens = if meta.path.is_ident("sized_text") {
+                let max = argument(&meta, "max", 0, 255)?.unwrap_or(255);
+                kind = "text";
+                quote!(SizedText(#max))
+            } else if meta.path.is_ident("exact_text") {
+                let size = argument(&meta, "size", 0, 255)?
+                    .ok_or_else(|| meta.error("expected exact_text(size = ..)"))?;
+                kind = "text";
+                quote!(ExactText(#size))
+            } else if meta.path.is_ident("sized_number") {
+                let size = argument(&meta, "size", 1, 8)?
+                    .ok_or_else(|| meta.error("expected sized_number(size = ..)"))?;
+                kind = "number";
+                quote!(SizedNumber(#size))
+            } else if meta.path.is_ident("percentage") {
+                kind = "number";
+                quote!(Percentage)
+            } else if meta.path.is_ident("mac_list") {
+                kind = "MAC list";
+                quote!(MacList)
+            } else if meta.path.is_ident("charset") {
+                let lit: LitStr = meta.value()?.parse()?;
+                check_charset(&lit)?;
+                charset = Some(lit.value());
+                return Ok(());
+            } else {
+                return Err(meta.error(
+                    "expected sized_text, exact_text, sized_number, percentage, mac_list or charset",
+                ));
+            };
+            if layout.replace(tokens).is_some() {
+                return Err(meta.error("a field has a single layout"));
+            }
+            Ok(())
+        })?;
+    }
+    match layout {
+        None => Err(Error::new_spanned(field, "missing #[htip(..)] layout")),
+        Some(_) if charset.is_some() && kind != "text" => {
+            Err(Error::new_spanned(field, "charset of a non-text field"))
+        }
+        Some(_) if value_kind(&field.ty).filter(|ty| *ty != kind).is_some() => {
+            Err(Error::new_spanned(
+                &field.ty,
+                format!("a {} layout needs a {} field", kind, kind),
+            ))
+        }
+        Some(layout) => Ok((layout, charset)),
+    }
+}
+
+fn expand(input: &DeriveInput) -> Result<TokenStream2> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) if !fields.named.is_empty() => &fields.named,
+            _ => {
+                return Err(Error::new_spanned(
+                    input,
+                    "HtipTlv needs a struct with named fields",
+                ))
+            }
+        },
+        _ => return Err(Error::new_spanned(input, "HtipTlv needs a struct")),
+    };
+    let TlvAttrs {
+        name,
+        tlv_type,
+        prefix,
+    } = tlv_attrs(input)?;
+
+    let idents = fields
+        .iter()
+        .filter_map(|field| field.ident.as_ref())
+        .collect::<Vec<_>>();
+    let names = idents
+        .iter()
+        .map(|ident| ident.to_string().trim_start_matches("r#").to_string());
+    let (layouts, charsets): (Vec<_>, Vec<_>) = fields
+        .iter()
+        .map(field_attrs)
+        .collect::<Result<Vec<_>>>()?
+        .into_iter()
+        .unzip();
+    let charsets = charsets.iter().map(|charset| match charset {
+        Some(charset) => quote!(::std::option::Option::Some(#charset)),
+        None => quote!(::std::option::Option::None),
+    });
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::rust_htip::typed::HtipTlv for #ident #ty_generics #where_clause {
+            const NAME: &'static str = #name;
+            const TLV_TYPE: u8 = #tlv_type;
+            const PREFIX: &'static [u8] = &[#(#prefix),*];
+            const FIELDS: &'static [::rust_htip::typed::FieldInfo] = &[#(
+                ::rust_htip::typed::FieldInfo {
+                    name: #names,
+                    layout: ::rust_htip::typed::Layout::#layouts,
+                    charset: #charsets,
+                }
+            ),*];
+
+            fn from_values(
+                values: ::std::vec::Vec<::rust_htip::ParseData>,
+            ) -> ::std::option::Option<Self> {
+                let mut values = values.into_iter();
+                ::std::option::Option::Some(Self {
+                    #(#idents: ::rust_htip::typed::FieldValue::from_data(values.next()?)?,)*
+                })
+            }
+
+            fn to_values(&self) -> ::std::vec::Vec<::rust_htip::ParseData> {
+                ::std::vec![#(::rust_htip::typed::FieldValue::to_data(&self.#idents)),*]
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syn::parse_quote;
+
+    fn error(input: DeriveInput) -> String {
+        match expand(&input) {
+            Ok(_) => panic!("this should not derive!"),
+            Err(err) => err.to_string(),
+        }
+    }
+
+    #[test]
+    fn field_types_must_match_their_layout() {
+        let input = parse_quote! {
+            #[htip(key = "01")]
+            struct Status {
+                #[htip(percentage)]
+                name: String,
+            }
+        };
+        assert_eq!(error(input), "a number layout needs a number field");
+        let input = parse_quote! {
+            #[htip(key = "01")]
+            struct Status {
+                #[htip(sized_text)]
+                load: u8,
+            }
+        };
+        assert_eq!(error(input), "a text layout needs a text field");
+        let input = parse_quote! {
+            #[htip(key = "01")]
+            struct Status {
+                #[htip(sized_text)]
+                name: ::std::string::String,
+                #[htip(mac_list)]
+                peers: Vec<MacAddr6>,
+            }
+        };
+        assert!(expand(&input).is_ok());
+    }
+}
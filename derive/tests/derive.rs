@@ -0,0 +1,86 @@
+use macaddr::MacAddr6;
+use rust_htip::typed::{FieldInfo, HtipTlv, Layout};
+use rust_htip::{Dispatcher, Lint, TlvKey};
+
+const MANDATORY: &[u8] = b"\x02\x07\x04ABCDEF\x04\x07\x03ABCDEF\x06\x02\x00\x78";
+
+#[derive(Debug, PartialEq, HtipTlv)]
+#[htip(key = "01 14")]
+struct CpuUsage {
+    #[htip(percentage)]
+    value: u8,
+}
+
+#[derive(Debug, PartialEq, HtipTlv)]
+#[htip(oui = "0a:1b:2c", key = "01", name = "vendor-status")]
+struct VendorStatus {
+    #[htip(sized_text(max = 15), charset = "A-Z")]
+    state: String,
+    #[htip(exact_text(size = 2))]
+    code: String,
+    #[htip(sized_number(size = 2))]
+    port: u16,
+    #[htip(sized_number(size = 6))]
+    uptime: u64,
+    #[htip(mac_list)]
+    peers: Vec<MacAddr6>,
+}
+
+fn status(state: &str) -> VendorStatus {
+    VendorStatus {
+        state: state.to_string(),
+        code: "A1".to_string(),
+        port: 8,
+        uptime: 3600,
+        peers: vec![MacAddr6::new(0, 0x11, 0x22, 0x33, 0x44, 0x55)],
+    }
+}
+
+fn frame(tlv: &[u8]) -> Vec<u8> {
+    [MANDATORY, tlv, b"\x00\x00"].concat()
+}
+
+#[test]
+fn attributes_describe_the_layout() {
+    assert_eq!(CpuUsage::key(), TlvKey::htip(vec![1, 0x14]));
+    assert_eq!(VendorStatus::NAME, "vendor-status");
+    assert_eq!(VendorStatus::TLV_TYPE, 127);
+    assert_eq!(VendorStatus::PREFIX, b"\x0a\x1b\x2c\x01");
+    assert_eq!(
+        VendorStatus::FIELDS[0],
+        FieldInfo {
+            name: "state",
+            layout: Layout::SizedText(15),
+            charset: Some("A-Z"),
+        }
+    );
+    assert_eq!(VendorStatus::FIELDS[3].layout, Layout::SizedNumber(6));
+}
+
+#[test]
+fn derived_types_roundtrip() {
+    let mut dsp = Dispatcher::new();
+    dsp.register::<CpuUsage>();
+    dsp.register::<VendorStatus>();
+
+    let tlvs = [
+        CpuUsage { value: 42 }.encode().unwrap(),
+        status("IDLE").encode().unwrap(),
+    ]
+    .concat();
+    let frame = frame(&tlvs);
+    let info = dsp.parse(&frame).ok().unwrap();
+    assert!(info.errors.is_empty());
+    assert_eq!(CpuUsage::find(&info), Some(CpuUsage { value: 42 }));
+    assert_eq!(VendorStatus::find(&info), Some(status("IDLE")));
+}
+
+#[test]
+fn derived_types_are_linted() {
+    let mut dsp = Dispatcher::new();
+    dsp.register::<VendorStatus>();
+    let frame = frame(&status("idle").encode().unwrap());
+    let info = dsp.parse(&frame).ok().unwrap();
+    assert_eq!(info.lints[0].lint, Lint::Warning(1));
+    assert_eq!(info.lints[0].extra_info.as_deref(), Some("field state"));
+}
@@ -1,25 +1,91 @@
-use rust_htip::{Dispatcher, InvalidFrame};
-
-pub fn parse_captured<T: pcap::Activated>(mut capture: pcap::Capture<T>) {
-    //static setup
-    //1. setup our filter (broadcast + lldp)
-    capture
-        .filter("ether broadcast && ether proto 0x88cc")
-        .expect("pcap: unable to set filter");
-    //2. get a dispatcher instance
+use rust_htip::capture::{self, ETHER_HEADER_LEN};
+use rust_htip::crossframe::CrossFrameLinter;
+use rust_htip::locale::{Language, Localize};
+use rust_htip::policy::LintPolicy;
+use rust_htip::schema::Schema;
+use rust_htip::{Dispatcher, FrameInfo, Lint, PacketInfo};
+
+/// Parses the frames of a capture, linting each frame on its own & against
+/// the frames before it
+pub struct Session {
+    pub dispatcher: Dispatcher<'static>,
+    pub history: CrossFrameLinter,
+}
+
+/// A session which lints with the policy at `policy`, parses in `mode`
+/// (normal, strict or lenient) & with the tlv layouts of the schema at
+/// `schema`, if any
+pub fn session(
+    policy: Option<&str>,
+    mode: Option<&str>,
+    schema: Option<&str>,
+) -> Result<Session, String> {
     let mut dispatcher = Dispatcher::new();
+    if let Some(mode) = mode {
+        dispatcher.set_mode(mode.parse()?);
+    }
+    if let Some(path) = schema {
+        let schema = Schema::load(path).map_err(|err| format!("{}: {}", path, err))?;
+        dispatcher.add_schema(schema);
+    }
+    if let Some(path) = policy {
+        let policy = LintPolicy::load(path).map_err(|err| format!("{}: {}", path, err))?;
+        dispatcher.set_policy(policy);
+    }
+    Ok(Session {
+        dispatcher,
+        history: CrossFrameLinter::new(),
+    })
+}
+
+/// Print the catalogue entry of the lint `code` (e.g. `W2` or `trailing-data`)
+pub fn explain(code: &str) {
+    match code.parse::<Lint>().map(|lint| lint.info()) {
+        Ok(Some(info)) => println!("{}", info.localized(Language::from_env())),
+        Ok(None) => eprintln!("unknown lint: {}", code),
+        Err(err) => eprintln!("{}", err),
+    }
+}
 
-    loop {
+/// Remove `option` & its value from `args`, returning the value
+pub fn take_option(args: &mut Vec<String>, option: &str) -> Result<Option<String>, String> {
+    match args.iter().position(|arg| arg == option) {
+        Some(index) if index + 1 < args.len() => Ok(args.drain(index..=index + 1).nth(1)),
+        Some(_) => Err(format!("{} needs a value", option)),
+        None => Ok(None),
+    }
+}
+
+/// Calls `handle` for every captured ethernet packet carrying an htip frame,
+/// along with the capture metadata of the packet. Packets are numbered
+/// among every packet of the capture, so only a live capture should be
+/// filtered beforehand.
+pub fn for_each_frame<T, F>(mut capture: pcap::Capture<T>, mut handle: F)
+where
+    T: pcap::Activated,
+    F: FnMut(&[u8], PacketInfo),
+{
+    if capture.get_datalink() != pcap::Linktype(1) {
+        eprintln!("not an ethernet capture");
+        return;
+    }
+
+    for index in 0.. {
         let cap_data = capture.next();
         match cap_data {
             Ok(data) => {
-                //strip the ethernet header (14 bytes)
-                if let Some(htip_frame) = data.get(14..) {
-                    let parse_result = dispatcher.parse(htip_frame);
-                    match parse_result {
-                        Ok(data) => println!("{}\n", data),
-                        Err(err) => handle_bad_frame(err, &mut dispatcher),
-                    }
+                if !capture::is_htip(&data) {
+                    continue;
+                }
+                let header = data.header;
+                let packet = PacketInfo::new(
+                    index,
+                    capture::timestamp(header.ts.tv_sec as u64, header.ts.tv_usec as u64),
+                    header.caplen,
+                    header.len,
+                );
+                if let Some(packet) = capture::with_source(packet, &data) {
+                    handle(&data, packet);
                 }
             }
             //if calling next() causes an error (e.g. no more data), we bail
@@ -28,8 +94,32 @@ pub fn parse_captured<T: pcap::Activated>(mut capture: pcap::Capture<T>) {
     }
 }
 
-fn handle_bad_frame(frame: InvalidFrame, dispatcher: &mut Dispatcher) {
-    println!("BAD FRAME! possibly incorrect parse results!\n");
-    let stuff = frame.parse(dispatcher);
-    println!("{}\n", stuff);
+/// Parse the htip frame of an ethernet packet, and report if its TLVs
+/// were well constructed. A bad frame is parsed as far as possible.
+pub fn parse_packet<'a>(
+    session: &mut Session,
+    data: &'a [u8],
+    packet: PacketInfo,
+) -> (FrameInfo<'a>, bool) {
+    let dispatcher = &mut session.dispatcher;
+    //strip the ethernet header (14 bytes)
+    let htip_frame = &data[ETHER_HEADER_LEN..];
+    let (info, well_constructed) = match dispatcher.parse(htip_frame) {
+        Ok(info) => (info.with_packet(packet), true),
+        Err(err) => (err.parse(dispatcher).with_packet(packet), false),
+    };
+    let mut info = info;
+    let mut lints = session.history.lint(&info);
+    info.lints.append(&mut lints);
+    //after the lints of the packet & of the frames before it
+    dispatcher.finish(&mut info);
+    (info, well_constructed)
+}
+
+/// Print a parsed htip frame, as returned by [parse_packet]
+pub fn print_info(info: &FrameInfo, well_constructed: bool) {
+    if !well_constructed {
+        println!("BAD FRAME! possibly incorrect parse results!\n");
+    }
+    println!("{}\n", info.localized(Language::from_env()));
 }
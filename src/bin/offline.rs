@@ -1,18 +1,366 @@
+use rust_htip::capture::{self, ETHER_HEADER_LEN};
+use rust_htip::conformance::{ConformanceTest, Verdict};
+use rust_htip::filter::FrameFilter;
+use rust_htip::fixer::{Fix, FrameFixer};
+use rust_htip::junit::JUnitReport;
+use rust_htip::locale::Language;
+use rust_htip::pcapng::{self, PcapngReader};
+use rust_htip::writer::{FrameWriter, PcapWriter, PcapngWriter};
+use rust_htip::{FrameInfo, PacketInfo};
 use std::env;
+use std::fs;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read};
+use std::process;
 mod common;
 
-//Accepts a number of file names
+/// Link type of ethernet interfaces
+const ETHERNET: u16 = 1;
+
+/// What to do with every parsed frame
+enum Output {
+    /// Print everything, along with the repairs of every frame if fixing
+    Print { fixer: Option<FrameFixer> },
+    /// Write the selected frames into a capture file, annotated with
+    /// their lints & errors, or repaired & annotated with the repairs
+    Write {
+        filter: FrameFilter,
+        fixer: Option<FrameFixer>,
+        writer: Box<dyn FrameWriter>,
+        written: usize,
+    },
+    /// Report the selected frames as JUnit test cases into an xml file
+    JUnit {
+        filter: FrameFilter,
+        report: JUnitReport,
+        path: String,
+    },
+    /// Check the selected frames for conformance, reporting into a json,
+    /// JUnit xml or markdown file
+    Report {
+        filter: FrameFilter,
+        test: ConformanceTest,
+        path: String,
+    },
+}
+
+/// Repair the htip frame of an ethernet packet, keeping its ethernet header.
+/// `info` is the frame as parsed by [common::parse_packet], so only the
+/// lints left by the policy are repaired.
+fn fix_packet(
+    session: &mut common::Session,
+    fixer: &FrameFixer,
+    data: &[u8],
+    info: &FrameInfo,
+) -> (Vec<u8>, Vec<Fix>) {
+    let fixed = fixer.fix(&mut session.dispatcher, info);
+    let packet = [&data[..ETHER_HEADER_LEN], &fixed.frame[..]].concat();
+    (packet, fixed.fixes)
+}
+
+impl Output {
+    fn handle(&mut self, session: &mut common::Session, data: &[u8], packet: PacketInfo) {
+        match self {
+            Output::Print { fixer } => {
+                let (info, well_constructed) = common::parse_packet(session, data, packet);
+                common::print_info(&info, well_constructed);
+                if let Some(fixer) = fixer {
+                    let (_, fixes) = fix_packet(session, fixer, data, &info);
+                    for fix in fixes {
+                        println!("Fixed: {}", fix);
+                    }
+                    println!();
+                }
+            }
+            Output::Write {
+                filter,
+                fixer,
+                writer,
+                written,
+            } => {
+                let (info, _) = common::parse_packet(session, data, packet.clone());
+                if !filter.matches(&info) {
+                    return;
+                }
+                let result = match fixer {
+                    Some(fixer) => {
+                        let (fixed, fixes) = fix_packet(session, fixer, data, &info);
+                        let fixes: Vec<String> = fixes.iter().map(Fix::to_string).collect();
+                        let packet = PacketInfo {
+                            caplen: fixed.len() as u32,
+                            len: fixed.len() as u32,
+                            ..packet
+                        };
+                        writer.write_packet(&fixed, &packet, &fixes)
+                    }
+                    None => writer.write_packet(
+                        data,
+                        &packet,
+                        &info.annotations_in(Language::from_env()),
+                    ),
+                };
+                match result {
+                    Ok(()) => *written += 1,
+                    Err(err) => eprintln!("write error: {}", err),
+                }
+            }
+            Output::JUnit { filter, report, .. } => {
+                let (info, _) = common::parse_packet(session, data, packet);
+                if filter.matches(&info) {
+                    report.add_frame(&info);
+                }
+            }
+            Output::Report { filter, test, .. } => {
+                let (info, _) = common::parse_packet(session, data, packet);
+                if filter.matches(&info) {
+                    test.add(&info);
+                }
+            }
+        }
+    }
+}
+
+fn parse_captured<T: pcap::Activated>(
+    capture: pcap::Capture<T>,
+    session: &mut common::Session,
+    output: &mut Output,
+) {
+    common::for_each_frame(capture, |data, packet| output.handle(session, data, packet));
+}
+
+/// Parse a pcapng file, keeping the interface & comments of every packet
+fn parse_pcapng(
+    file: File,
+    session: &mut common::Session,
+    output: &mut Output,
+) -> Result<(), pcapng::PcapngError> {
+    let mut reader = PcapngReader::new(BufReader::new(file))?;
+
+    for index in 0.. {
+        let packet = match reader.next() {
+            Some(packet) => packet?,
+            None => break,
+        };
+        //only keep broadcast lldp, numbering every packet
+        let interface = reader
+            .interface(packet.interface_id)
+            .expect("the reader checks interface ids");
+        let data = &packet.data;
+        if interface.link_type != ETHERNET || !capture::is_htip(data) {
+            continue;
+        }
+
+        let name = interface
+            .name
+            .clone()
+            .unwrap_or_else(|| format!("interface {}", packet.interface_id));
+        let info = PacketInfo::new(
+            index,
+            packet.timestamp,
+            packet.data.len() as u32,
+            packet.len,
+        )
+        .with_interface(name)
+        .with_comments(packet.comments.clone());
+        if let Some(info) = capture::with_source(info, data) {
+            output.handle(session, data, info);
+        }
+    }
+    Ok(())
+}
+
+fn is_pcapng(path: &str) -> bool {
+    let mut magic = [0u8; 4];
+    File::open(path)
+        .and_then(|mut file| file.read_exact(&mut magic))
+        .map(|_| pcapng::is_pcapng(&magic))
+        .unwrap_or(false)
+}
+
+fn usage() {
+    println!(
+        "USAGE: offline [--policy policy.txt] [--mode strict|lenient] [--schema schema.toml] [--fix] [--write output.pcap[ng] [selection...]] file...\n\
+        \x20      offline [--policy policy.txt] --report report.json|report.xml|report.md [selection...] file...\n\
+        \x20      offline [--policy policy.txt] --junit frames.xml [selection...] file...\n\
+        \x20      offline --explain CODE\n\
+        Parses pcap & pcapng files and prints their htip frames.\n\
+        --explain describes lint CODE (e.g. W2 or trailing-data) & the spec rule behind it.\n\
+        --policy disables, overrides & suppresses lints as configured in policy.txt.\n\
+        --mode strict fails frames on every lint, --mode lenient accepts common vendor deviations.\n\
+        --schema parses the tlvs laid out in schema.toml, e.g. proprietary vendor extensions.\n\
+        With --write, the selected frames are written into output instead;\n\
+        a .pcapng output also carries the lints & errors of each frame as comments.\n\
+        --fix repairs trivially fixable lints (missing End TLV, trailing bytes, invalid characters):\n\
+        the repairs are printed, or the repaired frames are written along with their repairs.\n\
+        With --report, the selected frames (e.g. --source of a single device) are checked for conformance,\n\
+        and every requirement, its verdict & evidence frames are reported as json (.json),\n\
+        JUnit xml (.xml, a test case per requirement) or markdown; the exit status is 1 on a FAIL verdict.\n\
+        With --junit, every selected frame is a JUnit test case named after its file, failing on\n\
+        errors; the exit status is 1 on a failed frame.\n\
+        selection (every given option must match):\n  \
+          --source MAC       frames sent from MAC\n  \
+          --maker-code CODE  frames advertising maker code CODE\n  \
+          --lint CODE        broken frames with lint CODE (e.g. W2, E1)\n  \
+          --errors           broken frames with parsing errors"
+    );
+}
+
+/// Split the arguments into the session, the output & the files to parse
+fn parse_args(mut args: Vec<String>) -> Result<(common::Session, Output, Vec<String>), String> {
+    let policy = common::take_option(&mut args, "--policy")?;
+    let mode = common::take_option(&mut args, "--mode")?;
+    let schema = common::take_option(&mut args, "--schema")?;
+    let session = common::session(policy.as_deref(), mode.as_deref(), schema.as_deref())?;
+
+    let mut files = vec![];
+    let mut output_path = None;
+    let mut report_path = None;
+    let mut junit_path = None;
+    let mut filter = FrameFilter::new();
+    let mut fixer = None;
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("{} needs a value", arg));
+        match arg.as_str() {
+            "--write" => output_path = Some(value()?),
+            "--report" => report_path = Some(value()?),
+            "--junit" => junit_path = Some(value()?),
+            "--source" => {
+                let source = value()?;
+                let mac = source
+                    .parse()
+                    .map_err(|_| format!("invalid MAC {}", source))?;
+                filter = filter.source(mac);
+            }
+            "--maker-code" => filter = filter.maker_code(&value()?),
+            "--lint" => filter = filter.lint(value()?.parse()?),
+            "--errors" => filter = filter.with_errors(),
+            "--fix" => fixer = Some(FrameFixer::new()),
+            _ => files.push(arg),
+        }
+    }
+
+    let output = match (output_path, report_path, junit_path) {
+        (None, None, None) => Output::Print { fixer },
+        (None, Some(path), None) => Output::Report {
+            filter,
+            test: ConformanceTest::new(),
+            path,
+        },
+        (None, None, Some(path)) => Output::JUnit {
+            filter,
+            report: JUnitReport::new(&path),
+            path,
+        },
+        (Some(path), None, None) => {
+            let file = BufWriter::new(File::create(&path).map_err(|err| err.to_string())?);
+            let writer: Box<dyn FrameWriter> = if path.ends_with(".pcapng") {
+                Box::new(PcapngWriter::new(file).map_err(|err| err.to_string())?)
+            } else {
+                Box::new(PcapWriter::new(file).map_err(|err| err.to_string())?)
+            };
+            Output::Write {
+                filter,
+                fixer,
+                writer,
+                written: 0,
+            }
+        }
+        _ => return Err("--write, --report & --junit are exclusive".to_string()),
+    };
+    Ok((session, output, files))
+}
+
+//Accepts a number of file names (pcap or pcapng)
 fn main() {
     let args: Vec<String> = env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "--help") {
+        usage();
+        return;
+    }
+    if let Some(index) = args.iter().position(|arg| arg == "--explain") {
+        match args.get(index + 1) {
+            Some(code) => common::explain(code),
+            None => usage(),
+        }
+        return;
+    }
+    let (mut session, mut output, files) = match parse_args(args) {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            eprintln!("{}", err);
+            usage();
+            process::exit(2);
+        }
+    };
 
-    for arg in args {
+    for arg in files {
         print!("opening file {} ...", arg);
+        if let Output::JUnit { report, .. } = &mut output {
+            report.set_capture(&arg);
+        }
+        if is_pcapng(&arg) {
+            match File::open(&arg) {
+                Ok(file) => {
+                    println!("OK");
+                    if let Err(err) = parse_pcapng(file, &mut session, &mut output) {
+                        println!("FAILED! error: {}", err);
+                    }
+                }
+                Err(err) => println!("FAILED! error: {}", err),
+            }
+            continue;
+        }
         match pcap::Capture::from_file(arg) {
             Ok(capture) => {
                 println!("OK");
-                common::parse_captured(capture);
+                parse_captured(capture, &mut session, &mut output);
             }
             Err(err) => println!("FAILED! error: {}", err),
         }
     }
+
+    match output {
+        Output::Write {
+            mut writer,
+            written,
+            ..
+        } => match writer.finish() {
+            Ok(()) => println!("wrote {} frames", written),
+            Err(err) => eprintln!("write error: {}", err),
+        },
+        Output::Report { test, path, .. } => {
+            let report = test.report();
+            let contents = if path.ends_with(".json") {
+                report.to_json()
+            } else if path.ends_with(".xml") {
+                JUnitReport::from_conformance(&path, &report).to_xml()
+            } else {
+                report.to_markdown()
+            };
+            match fs::write(&path, contents) {
+                Ok(()) => println!(
+                    "conformance: {} ({} frames)",
+                    report.verdict(),
+                    report.frames
+                ),
+                Err(err) => eprintln!("{}: {}", path, err),
+            }
+            //let CI gate on the verdict
+            if report.verdict() == Verdict::Fail {
+                process::exit(1);
+            }
+        }
+        Output::JUnit { report, path, .. } => {
+            if let Err(err) = fs::write(&path, report.to_xml()) {
+                eprintln!("{}: {}", path, err);
+            }
+            //let CI gate on the failed frames
+            if report.failures() > 0 {
+                process::exit(1);
+            }
+        }
+        Output::Print { .. } => {}
+    }
 }
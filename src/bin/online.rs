@@ -1,37 +1,109 @@
 use pcap::Device;
+use rust_htip::capture::HTIP_FILTER;
 use std::env;
+use std::sync::mpsc;
+use std::thread;
 
 mod common;
 
+fn usage(program: &str) {
+    println!(
+        "USAGE: sudo {0} [--policy policy.txt] [--mode strict|lenient] [--schema schema.toml] [--all | interface_name...]\n\
+        \x20      {0} --explain CODE\n\
+        if no interface_name is given the first available interface will be used.\n\
+        --all captures from every available interface.\n\
+        --explain describes lint CODE (e.g. W2 or trailing-data) & the spec rule behind it.\n\
+        --policy disables, overrides & suppresses lints as configured in policy.txt.\n\
+        --mode strict fails frames on every lint, --mode lenient accepts common vendor deviations.\n\
+        --schema parses the tlvs laid out in schema.toml, e.g. proprietary vendor extensions.\n\
+        Every frame is tagged with the interface it was captured on.",
+        program
+    );
+}
+
 fn main() -> Result<(), pcap::Error> {
-    let args: Vec<String> = env::args().collect();
-
-    //we don't have a specified network interface
-    if args.len() != 2 { 
-        let device = Device::lookup()?;
-        match pcap::Capture::from_device(device)?.open() {
-            Ok(cap) => common::parse_captured(cap),
-            Err(_err) => eprintln!(
-                "device open error, requires root privilege\n\
-                Usage: sudo {}\nerror: {}",
-                &args[0], _err
-            ),
+    let mut args: Vec<String> = env::args().collect();
+
+    if args.iter().skip(1).any(|arg| arg == "--help") {
+        usage(&args[0]);
+        return Ok(());
+    }
+    if let Some(index) = args.iter().position(|arg| arg == "--explain") {
+        match args.get(index + 1) {
+            Some(code) => common::explain(code),
+            None => usage(&args[0]),
         }
-    // exactly two args from here on
-    // help case
-    } else if args[1] == "--help" {
-        println!(
-            "USAGE: sudo ./target/debug/online [interface_name]\n\
-            if interface_name is empty the first available interface will be used.");
-    //explicitly specified network interface in args[1]
-    } else {
-        match pcap::Capture::from_device(args[1].as_str())?.open() {
-            Ok(cap) => common::parse_captured(cap),
-            Err(err) => eprintln!(
-                "device open error: {}\n\
-                error: {}",
-                &args[1], err),
+        return Ok(());
+    }
+
+    //take out the session options, everything else is about interfaces
+    let session = common::take_option(&mut args, "--policy").and_then(|policy| {
+        let mode = common::take_option(&mut args, "--mode")?;
+        let schema = common::take_option(&mut args, "--schema")?;
+        common::session(policy.as_deref(), mode.as_deref(), schema.as_deref())
+    });
+    let mut session = match session {
+        Ok(session) => session,
+        Err(err) => {
+            eprintln!("{}", err);
+            usage(&args[0]);
+            return Ok(());
         }
+    };
+
+    let devices = match args.get(1).map(String::as_str) {
+        //we don't have a specified network interface
+        None => vec![Device::lookup()?],
+        Some("--all") => Device::list()?,
+        //explicitly specified network interfaces in args[1..]
+        Some(_) => args[1..].iter().map(|name| name.as_str().into()).collect(),
+    };
+
+    //open every device, skipping (but reporting) the ones that fail
+    let captures = devices
+        .into_iter()
+        .filter_map(|device| {
+            let name = device.name.clone();
+            let capture = pcap::Capture::from_device(device)
+                .and_then(|cap| cap.open())
+                .and_then(|mut cap| cap.filter(HTIP_FILTER).map(|_| cap));
+            match capture {
+                Ok(cap) => Some((name, cap)),
+                Err(err) => {
+                    eprintln!(
+                        "device open error: {} (requires root privilege)\n\
+                        error: {}",
+                        name, err
+                    );
+                    None
+                }
+            }
+        })
+        .collect::<Vec<_>>();
+
+    if captures.is_empty() {
+        usage(&args[0]);
+        return Ok(());
+    }
+
+    //one capture thread per interface, all merged into a single channel
+    let (sender, receiver) = mpsc::channel();
+    for (name, cap) in captures {
+        let sender = sender.clone();
+        thread::spawn(move || {
+            common::for_each_frame(cap, |data, packet| {
+                //the receiver only goes away when we are exiting anyway
+                let packet = packet.with_interface(name.clone());
+                let _ = sender.send((data.to_vec(), packet));
+            })
+        });
+    }
+    //drop our own sender, so the loop below ends when all captures end
+    drop(sender);
+
+    for (data, packet) in receiver {
+        let (info, well_constructed) = common::parse_packet(&mut session, &data, packet);
+        common::print_info(&info, well_constructed);
     }
     Ok(())
 }
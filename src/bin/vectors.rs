@@ -0,0 +1,72 @@
+use rust_htip::capture::{ETHER_HEADER_LEN, HTIP_FILTER};
+use rust_htip::vectors::{self, VectorGenerator};
+use rust_htip::writer::{FrameWriter, PcapWriter, PcapngWriter};
+use rust_htip::Dispatcher;
+use std::env;
+use std::fs::File;
+use std::io::BufWriter;
+
+fn usage() {
+    println!(
+        "USAGE: vectors output.pcap[ng] [base.pcap]\n\
+        Writes malformed htip frames, each labelled with the parsing error or lint it should trigger.\n\
+        The frames are mutations of the first htip frame of base.pcap, or of a built-in valid frame.\n\
+        A .pcapng output carries the labels as comments, they are printed either way.\n\
+        Frames that don't trigger their label, e.g. as base.pcap is malformed already, are skipped."
+    );
+}
+
+/// The htip frame of the first broadcast lldp packet of the capture at `path`
+fn base_frame(path: &str) -> Result<Vec<u8>, String> {
+    let mut capture = pcap::Capture::from_file(path).map_err(|err| err.to_string())?;
+    capture.filter(HTIP_FILTER).map_err(|err| err.to_string())?;
+    match capture.next() {
+        Ok(packet) if packet.data.len() > ETHER_HEADER_LEN => {
+            Ok(packet.data[ETHER_HEADER_LEN..].to_vec())
+        }
+        _ => Err(format!("{}: no htip frame", path)),
+    }
+}
+
+fn run(output: &str, base: Option<&str>) -> Result<(), String> {
+    let frame = match base {
+        Some(path) => base_frame(path)?,
+        None => VectorGenerator::sample(),
+    };
+    let generator = VectorGenerator::new(&frame).map_err(|_| "invalid base frame".to_string())?;
+    //vectors that don't trigger their label are reported, not written
+    let mut dispatcher = Dispatcher::new();
+    let (vectors, unchecked): (Vec<_>, Vec<_>) = generator
+        .vectors()
+        .into_iter()
+        .partition(|vector| vector.check(&mut dispatcher));
+    for vector in &unchecked {
+        eprintln!("skipped {}", vector);
+    }
+
+    let file = BufWriter::new(File::create(output).map_err(|err| err.to_string())?);
+    let mut writer: Box<dyn FrameWriter> = if output.ends_with(".pcapng") {
+        Box::new(PcapngWriter::new(file).map_err(|err| err.to_string())?)
+    } else {
+        Box::new(PcapWriter::new(file).map_err(|err| err.to_string())?)
+    };
+    vectors::write_vectors(&vectors, writer.as_mut()).map_err(|err| err.to_string())?;
+    for (index, vector) in vectors.iter().enumerate() {
+        println!("{}: {}", index, vector);
+    }
+    println!("wrote {} frames", vectors.len());
+    Ok(())
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match args.as_slice() {
+        [output] => run(output, None),
+        [output, base] => run(output, Some(base)),
+        _ => {
+            usage();
+            return;
+        }
+    }
+    .unwrap_or_else(|err| eprintln!("{}", err));
+}
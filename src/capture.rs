@@ -0,0 +1,52 @@
+use crate::PacketInfo;
+use macaddr::MacAddr6;
+use std::convert::TryFrom;
+use std::time::Duration;
+
+/// Bpf filter of broadcast LLDP packets, for live captures
+pub const HTIP_FILTER: &str = "ether broadcast && ether proto 0x88cc";
+/// Size of the ethernet header in front of every HTIP frame
+pub const ETHER_HEADER_LEN: usize = 14;
+
+/// An ethernet packet carries an htip frame, i.e. it is a broadcast lldp
+/// packet
+pub fn is_htip(data: &[u8]) -> bool {
+    data.len() >= ETHER_HEADER_LEN && data[..6] == [0xff; 6] && data[12..14] == [0x88, 0xcc]
+}
+
+/// The timestamp of a packet header from its `tv_sec` & `tv_usec`, zero if
+/// out of range
+pub fn timestamp(seconds: u64, micros: u64) -> Duration {
+    Duration::from_secs(seconds)
+        .checked_add(Duration::from_micros(micros))
+        .unwrap_or_default()
+}
+
+/// Attach the source address of an ethernet packet, None if the packet
+/// is too short
+pub fn with_source(packet: PacketInfo, data: &[u8]) -> Option<PacketInfo> {
+    let source = <[u8; 6]>::try_from(data.get(6..12)?).ok()?;
+    data.get(ETHER_HEADER_LEN - 1)?;
+    Some(packet.with_source(MacAddr6::from(source)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timestamps_carry_over_their_micros() {
+        assert_eq!(timestamp(1, 2_500_000), Duration::new(3, 500_000_000));
+        //a negative tv_sec
+        assert_eq!(timestamp(-1i64 as u64, 1_000_000), Duration::default());
+    }
+
+    #[test]
+    fn only_broadcast_lldp_is_htip() {
+        let lldp = b"\xff\xff\xff\xff\xff\xff\x02\x00\x00\x00\x00\x01\x88\xcc";
+        assert!(is_htip(lldp));
+        assert!(!is_htip(&lldp[..13]));
+        let unicast = [&b"\x02"[..], &lldp[1..]].concat();
+        assert!(!is_htip(&unicast));
+    }
+}
@@ -0,0 +1,5 @@
+pub use crate::parsers::{
+    AnyBinary, Choice, Context, Count, ExactlySizedText, LengthPrefixed, Mac, NoData, Number,
+    NumberSize, Optional, Parser, Percentage, Record, Repeat, SizedNumber, SizedText, Text,
+    TypedData,
+};
@@ -0,0 +1,452 @@
+use crate::crossframe::UPDATE_INTERVAL;
+use crate::locale::Language;
+use crate::{FrameInfo, Lint, Severity, TlvKey, CATALOGUE};
+use std::collections::HashMap;
+use std::fmt;
+use std::time::Duration;
+
+/// Outcome of a requirement, ordered from best to worst after the
+/// requirements that could not be evaluated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verdict {
+    /// None of the frames could break the requirement, e.g. a cross-frame
+    /// lint with a single frame
+    NotEvaluated,
+    Pass,
+    /// Passed, but with warnings
+    Warning,
+    Fail,
+}
+
+impl Verdict {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Verdict::NotEvaluated => "not-evaluated",
+            Verdict::Pass => "pass",
+            Verdict::Warning => "warning",
+            Verdict::Fail => "fail",
+        }
+    }
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            self.as_str().replace('-', " ").to_ascii_uppercase()
+        )
+    }
+}
+
+/// A single requirement of the conformance report
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Requirement {
+    /// Stable identifier: the ID of a lint of the [CATALOGUE], `frames`
+    /// or `parsing`
+    pub id: &'static str,
+    /// The lint checking the requirement, if any
+    pub lint: Option<Lint>,
+    pub description: &'static str,
+    /// The rule of the specification, if any
+    pub reference: Option<&'static str>,
+    pub verdict: Verdict,
+    /// Indexes of the frames that broke the requirement
+    pub evidence: Vec<usize>,
+}
+
+/// The result of a [ConformanceTest]
+#[derive(Debug, Clone)]
+pub struct ConformanceReport {
+    /// Number of checked frames
+    pub frames: usize,
+    /// Time between the first & the last frame, if they were timestamped
+    pub duration: Option<Duration>,
+    pub requirements: Vec<Requirement>,
+}
+
+impl ConformanceReport {
+    /// The overall verdict: the worst verdict of all evaluated requirements
+    pub fn verdict(&self) -> Verdict {
+        self.requirements
+            .iter()
+            .map(|requirement| requirement.verdict)
+            .max()
+            .unwrap_or(Verdict::NotEvaluated)
+    }
+
+    pub fn to_json(&self) -> String {
+        let requirements = self
+            .requirements
+            .iter()
+            .map(|requirement| {
+                let evidence = requirement
+                    .evidence
+                    .iter()
+                    .map(usize::to_string)
+                    .collect::<Vec<_>>();
+                format!(
+                    "{{\"id\":{},\"code\":{},\"description\":{},\"reference\":{},\
+                    \"verdict\":\"{}\",\"evidence\":[{}]}}",
+                    json_string(requirement.id),
+                    json_option(requirement.lint.map(|lint| lint.code()).as_deref()),
+                    json_string(requirement.description),
+                    json_option(requirement.reference),
+                    requirement.verdict.as_str(),
+                    evidence.join(",")
+                )
+            })
+            .collect::<Vec<_>>();
+        format!(
+            "{{\"verdict\":\"{}\",\"frames\":{},\"duration\":{},\"requirements\":[{}]}}",
+            self.verdict().as_str(),
+            self.frames,
+            self.duration.map_or("null".to_string(), |duration| duration
+                .as_secs_f64()
+                .to_string()),
+            requirements.join(",")
+        )
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::from("# HTIP conformance report\n\n");
+        out += &format!("**Verdict: {}**\n\n", self.verdict());
+        out += &format!("Frames: {}\n", self.frames);
+        if let Some(duration) = self.duration {
+            out += &format!("Duration: {:.3}s\n", duration.as_secs_f64());
+        }
+        out += "\n| Requirement | Code | Verdict | Reference | Evidence frames |\n";
+        out += "|---|---|---|---|---|\n";
+        for requirement in &self.requirements {
+            let evidence = requirement
+                .evidence
+                .iter()
+                .map(usize::to_string)
+                .collect::<Vec<_>>();
+            out += &format!(
+                "| {}: {} | {} | {} | {} | {} |\n",
+                requirement.id,
+                markdown_cell(requirement.description),
+                requirement.lint.map(|lint| lint.code()).unwrap_or_default(),
+                requirement.verdict,
+                markdown_cell(requirement.reference.unwrap_or("")),
+                evidence.join(", ")
+            );
+        }
+        out
+    }
+}
+
+fn json_string(text: &str) -> String {
+    let mut out = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => out += "\\\"",
+            '\\' => out += "\\\\",
+            '\n' => out += "\\n",
+            c if (c as u32) < 0x20 => out += &format!("\\u{:04x}", c as u32),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn json_option(text: Option<&str>) -> String {
+    text.map_or("null".to_string(), json_string)
+}
+
+fn markdown_cell(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', " ")
+}
+
+/// Collects the frames of a single device (e.g. a capture filtered by
+/// source) into a pass/fail [ConformanceReport]. Every lint of the
+/// [CATALOGUE] is a requirement, along with:
+/// * `frames`: at least one frame was sent
+/// * `parsing`: every frame parsed without errors
+///
+/// A requirement fails if a frame reported its lint as an error, and passes
+/// with a warning if it was only reported as a warning. Transmit interval &
+/// TTL are checked by adding frames linted by a
+/// [CrossFrameLinter](crate::crossframe::CrossFrameLinter).
+///
+/// A requirement is not evaluated unless enough frames could have broken
+/// it: cross-frame lints need two frames, timing lints two timestamped
+/// frames, and lints of optional information (MAC address lists) a frame
+/// carrying it.
+///
+/// # Examples
+///
+/// ```
+/// use rust_htip::conformance::{ConformanceTest, Verdict};
+/// use rust_htip::Dispatcher;
+///
+/// let mut dispatcher = Dispatcher::new();
+/// let mut test = ConformanceTest::new();
+/// //no End TLV
+/// let frame = b"\x02\x07\x04ABCDEF\x04\x07\x03ABCDEF\x06\x02\x00\x78";
+/// test.add(&dispatcher.parse(frame).ok().unwrap());
+/// let report = test.report();
+/// assert_eq!(report.verdict(), Verdict::Fail);
+/// ```
+#[derive(Debug, Default)]
+pub struct ConformanceTest {
+    frames: usize,
+    /// Earliest & latest timestamps, frames may come in any order
+    first: Option<Duration>,
+    last: Option<Duration>,
+    /// Frames with parsing errors
+    errors: Vec<usize>,
+    /// Worst severity & frames of every reported lint
+    lints: HashMap<Lint, (Severity, Vec<usize>)>,
+    /// Number of frames every lint could have been reported on
+    candidates: HashMap<Lint, usize>,
+}
+
+impl ConformanceTest {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Check a frame. Frames are identified by the index of their packet,
+    /// or by the order they were added in.
+    pub fn add(&mut self, info: &FrameInfo) {
+        let index = info
+            .packet
+            .as_ref()
+            .map_or(self.frames, |packet| packet.index);
+        self.frames += 1;
+        if let Some(packet) = &info.packet {
+            let timestamp = packet.timestamp;
+            self.first = Some(self.first.map_or(timestamp, |first| first.min(timestamp)));
+            self.last = Some(self.last.map_or(timestamp, |last| last.max(timestamp)));
+        }
+        if !info.errors.is_empty() {
+            self.errors.push(index);
+        }
+        for entry in &info.lints {
+            let (severity, frames) = self
+                .lints
+                .entry(entry.lint)
+                .or_insert((Severity::Warning, vec![]));
+            if entry.severity == Severity::Error {
+                *severity = Severity::Error;
+            }
+            if frames.last() != Some(&index) {
+                frames.push(index);
+            }
+        }
+        for info in CATALOGUE.iter().filter(|lint| applies(lint.lint, info)) {
+            *self.candidates.entry(info.lint).or_default() += 1;
+        }
+    }
+
+    pub fn report(&self) -> ConformanceReport {
+        let mut requirements = vec![
+            Requirement {
+                id: "frames",
+                lint: None,
+                description: "The device sends HTIP frames",
+                reference: None,
+                verdict: if self.frames > 0 {
+                    Verdict::Pass
+                } else {
+                    Verdict::Fail
+                },
+                evidence: vec![],
+            },
+            Requirement {
+                id: "parsing",
+                lint: None,
+                description: "Every TLV of every frame is well formed",
+                reference: None,
+                verdict: if !self.errors.is_empty() {
+                    Verdict::Fail
+                } else if self.frames > 0 {
+                    Verdict::Pass
+                } else {
+                    Verdict::NotEvaluated
+                },
+                evidence: self.errors.clone(),
+            },
+        ];
+        for info in CATALOGUE {
+            let candidates = self.candidates.get(&info.lint).copied().unwrap_or(0);
+            let (verdict, evidence) = match self.lints.get(&info.lint) {
+                None if candidates < frames_needed(info.lint) => (Verdict::NotEvaluated, vec![]),
+                None => (Verdict::Pass, vec![]),
+                Some((Severity::Warning, frames)) => (Verdict::Warning, frames.clone()),
+                Some((Severity::Error, frames)) => (Verdict::Fail, frames.clone()),
+            };
+            requirements.push(Requirement {
+                id: info.id,
+                lint: Some(info.lint),
+                description: info.message(Language::English),
+                reference: info.reference,
+                verdict,
+                evidence,
+            });
+        }
+        ConformanceReport {
+            frames: self.frames,
+            duration: self.first.zip(self.last).map(|(first, last)| last - first),
+            requirements,
+        }
+    }
+}
+
+/// Whether `lint` could be reported on `info`
+fn applies(lint: Lint, info: &FrameInfo) -> bool {
+    let carries = |key: &TlvKey| info.info.iter().any(|(entry_key, _)| entry_key == key);
+    let reported = info.lints.iter().any(|entry| entry.lint == lint);
+    reported
+        || match lint {
+            Lint::Error(14) => carries(&TlvKey::htip(b"\x03".to_vec())),
+            Lint::Warning(4) | Lint::Warning(9) => info.packet.is_some(),
+            Lint::Warning(10) => {
+                info.packet.is_some() && carries(&TlvKey::htip(vec![1, UPDATE_INTERVAL]))
+            }
+            _ => true,
+        }
+}
+
+/// Number of frames `lint` needs to be evaluated: cross-frame lints compare
+/// a frame with the ones before it
+fn frames_needed(lint: Lint) -> usize {
+    match lint {
+        Lint::Error(13)
+        | Lint::Error(14)
+        | Lint::Warning(8)
+        | Lint::Warning(9)
+        | Lint::Warning(10) => 2,
+        _ => 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Dispatcher, PacketInfo};
+
+    const FRAME: &[u8] = b"\x02\x07\x04ABCDEF\x04\x07\x03ABCDEF\x06\x02\x00\x78";
+
+    fn test(frames: &[&[u8]]) -> ConformanceReport {
+        let mut dsp = Dispatcher::new();
+        let mut test = ConformanceTest::new();
+        for (index, frame) in frames.iter().enumerate() {
+            let packet = PacketInfo::new(index, Duration::from_secs(index as u64 * 30), 0, 0);
+            let info = match dsp.parse(frame) {
+                Ok(info) => info,
+                Err(err) => err.parse(&mut dsp),
+            };
+            test.add(&info.with_packet(packet));
+        }
+        test.report()
+    }
+
+    fn requirement<'r>(report: &'r ConformanceReport, id: &str) -> &'r Requirement {
+        match report.requirements.iter().find(|req| req.id == id) {
+            Some(requirement) => requirement,
+            None => panic!("no requirement {}", id),
+        }
+    }
+
+    #[test]
+    fn empty_captures_fail() {
+        let report = test(&[]);
+        assert_eq!(report.verdict(), Verdict::Fail);
+        assert_eq!(requirement(&report, "frames").verdict, Verdict::Fail);
+        assert_eq!(
+            requirement(&report, "parsing").verdict,
+            Verdict::NotEvaluated
+        );
+        assert_eq!(report.duration, None);
+    }
+
+    #[test]
+    fn unbreakable_requirements_are_not_evaluated() {
+        let good = [FRAME, b"\x00\x00"].concat();
+        let report = test(&[&good]);
+        assert_eq!(report.verdict(), Verdict::Pass);
+        assert_eq!(
+            requirement(&report, "missing-end-tlv").verdict,
+            Verdict::Pass
+        );
+        for id in &[
+            "ttl-shorter-than-period",
+            "update-interval-mismatch",
+            "conflicting-device-info",
+        ] {
+            assert_eq!(requirement(&report, id).verdict, Verdict::NotEvaluated);
+        }
+        let report = test(&[&good, &good]);
+        assert_eq!(
+            requirement(&report, "ttl-shorter-than-period").verdict,
+            Verdict::Pass
+        );
+        assert_eq!(Verdict::NotEvaluated.to_string(), "NOT EVALUATED");
+    }
+
+    #[test]
+    fn lints_are_evidence() {
+        let good = [FRAME, b"\x00\x00"].concat();
+        let report = test(&[&good, FRAME, FRAME]);
+        assert_eq!(report.verdict(), Verdict::Fail);
+        assert_eq!(report.frames, 3);
+        assert_eq!(report.duration, Some(Duration::from_secs(60)));
+        let missing_end = requirement(&report, "missing-end-tlv");
+        assert_eq!(missing_end.verdict, Verdict::Fail);
+        assert_eq!(missing_end.evidence, vec![1, 2]);
+        assert_eq!(requirement(&report, "parsing").verdict, Verdict::Pass);
+    }
+
+    #[test]
+    fn frames_may_come_out_of_order() {
+        let mut dsp = Dispatcher::new();
+        let mut test = ConformanceTest::new();
+        for (index, secs) in [30, 90, 0].iter().enumerate() {
+            let packet = PacketInfo::new(index, Duration::from_secs(*secs), 0, 0);
+            test.add(&dsp.parse(FRAME).ok().unwrap().with_packet(packet));
+        }
+        assert_eq!(test.report().duration, Some(Duration::from_secs(90)));
+    }
+
+    #[test]
+    fn warnings_pass_with_a_warning() {
+        //htip info 1 to 4, with trailing characters in the category
+        let htip = b"\xfe\x16\xe0\x27\x1a\x01\x01\x06Sensorcharacters\
+            \xfe\x0c\xe0\x27\x1a\x01\x02\x06CAFEBE\
+            \xfe\x07\xe0\x27\x1a\x01\x03\x01M\
+            \xfe\x07\xe0\x27\x1a\x01\x04\x01N\
+            \x00\x00";
+        let report = test(&[&[FRAME, htip].concat()]);
+        assert_eq!(report.verdict(), Verdict::Warning);
+        assert_eq!(requirement(&report, "trailing-data").evidence, vec![0]);
+    }
+
+    #[test]
+    fn parsing_errors_fail() {
+        //TTL value too short
+        let frame = b"\x02\x07\x04ABCDEF\x04\x07\x03ABCDEF\x06\x01\x00\x00\x00";
+        let report = test(&[frame]);
+        assert_eq!(requirement(&report, "parsing").verdict, Verdict::Fail);
+        assert_eq!(requirement(&report, "parsing").evidence, vec![0]);
+    }
+
+    #[test]
+    fn reports_are_rendered() {
+        let report = test(&[FRAME]);
+        let json = report.to_json();
+        assert!(json.starts_with("{\"verdict\":\"fail\",\"frames\":1,\"duration\":0,"));
+        assert!(json.contains(
+            "{\"id\":\"missing-end-tlv\",\"code\":\"E1\",\"description\":\"No End TLV\","
+        ));
+        assert!(json.contains("\"verdict\":\"fail\",\"evidence\":[0]}"));
+
+        let markdown = report.to_markdown();
+        assert!(markdown.contains("**Verdict: FAIL**"));
+        assert!(markdown.contains("| missing-end-tlv: "));
+        assert_eq!(json_string("a\"b\\\n\t"), "\"a\\\"b\\\\\\n\\u0009\"");
+    }
+}
@@ -0,0 +1,403 @@
+use crate::{FrameInfo, Lint, LintEntry, ParseData, TlvKey};
+use macaddr::MacAddr6;
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// A chassis ID: subtype & value
+type ChassisId = (u8, Vec<u8>);
+
+/// Info ID of the update interval, the 6 byte number of HTIP subtype 1
+pub(crate) const UPDATE_INTERVAL: u8 = 23;
+
+/// How long devices & MAC owners are remembered after their last frame, by
+/// default
+const EXPIRY: Duration = Duration::from_secs(3600);
+
+/// What was seen of a single device so far
+#[derive(Debug, Default)]
+struct Device {
+    /// Every distinct value of each identification field (IDs 1-4), in the
+    /// order they were seen; the last one is the current value
+    identity: HashMap<u8, Vec<String>>,
+    /// Capture time of the last frame on each interface, so that a frame
+    /// captured on several interfaces is not taken for a new one
+    last_seen: HashMap<Option<String>, Duration>,
+}
+
+impl Device {
+    /// Capture time of the last frame on any interface
+    fn last_seen(&self) -> Option<Duration> {
+        self.last_seen.values().max().copied()
+    }
+}
+
+/// Checks a sequence of frames (e.g. a capture) for information that
+/// conflicts between frames:
+/// 1. a chassis ID advertising a new device category, maker code, model name
+///    or model number, issue error(13)
+/// 2. a chassis ID switching back to a value it advertised before, issue
+///    warning(8) (flapping)
+/// 3. a MAC address (HTIP subtype 3) claimed by more than one chassis ID,
+///    issue error(14)
+/// 4. a TTL shorter than the time between two frames of a device, issue
+///    warning(9)
+/// 5. an update interval (info ID 23) off by more than a factor of 2 from
+///    the time between two frames of a device, issue warning(10)
+///
+/// Timing is only checked for frames with capture metadata (see
+/// [FrameInfo::with_packet()]), between frames of the same interface.
+/// Devices & MAC addresses unseen for an hour are forgotten, see
+/// [CrossFrameLinter::with_expiry()].
+///
+/// # Examples
+///
+/// ```
+/// use rust_htip::crossframe::CrossFrameLinter;
+/// use rust_htip::Dispatcher;
+///
+/// let mut dispatcher = Dispatcher::new();
+/// let mut linter = CrossFrameLinter::new();
+/// let frame = b"\x02\x07\x04ABCDEF\x04\x07\x03ABCDEF\x06\x02\x00\x78\x00\x00";
+/// let mut info = dispatcher.parse(frame).ok().unwrap();
+/// let lints = linter.lint(&info);
+/// info.lints.extend(lints);
+/// ```
+#[derive(Debug)]
+pub struct CrossFrameLinter {
+    devices: HashMap<ChassisId, Device>,
+    /// The chassis ID that first claimed each MAC address, & when it last
+    /// did
+    owners: HashMap<MacAddr6, (ChassisId, Option<Duration>)>,
+    expiry: Duration,
+}
+
+impl Default for CrossFrameLinter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CrossFrameLinter {
+    pub fn new() -> Self {
+        CrossFrameLinter {
+            devices: HashMap::new(),
+            owners: HashMap::new(),
+            expiry: EXPIRY,
+        }
+    }
+
+    /// Forget devices & MAC addresses unseen for longer than `expiry`; an
+    /// hour by default
+    pub fn with_expiry(self, expiry: Duration) -> Self {
+        CrossFrameLinter { expiry, ..self }
+    }
+
+    /// Check `info` against every frame seen before, then remember it.
+    /// Frames without a chassis ID are ignored.
+    pub fn lint(&mut self, info: &FrameInfo) -> Vec<LintEntry> {
+        let chassis = match find(info, &TlvKey::new(1, vec![])) {
+            Some(ParseData::TypedData(subtype, id)) => (*subtype, id.clone()),
+            _ => return vec![],
+        };
+        let now = info.packet.as_ref().map(|packet| packet.timestamp);
+        if let Some(now) = now {
+            self.expire(now);
+        }
+        let mut le = self.lint_macs(info, &chassis, now);
+
+        let device = self.devices.entry(chassis).or_default();
+        le.extend(CrossFrameLinter::lint_identity(info, device));
+        if let Some(packet) = &info.packet {
+            let last = device
+                .last_seen
+                .insert(packet.interface.clone(), packet.timestamp);
+            if let Some(period) = last.and_then(|last| packet.timestamp.checked_sub(last)) {
+                le.extend(CrossFrameLinter::lint_timing(info, period));
+            }
+        }
+        le
+    }
+
+    /// Forget what was last seen before `now - expiry`
+    fn expire(&mut self, now: Duration) {
+        let expiry = self.expiry;
+        let live = |seen: Option<Duration>| match seen {
+            Some(seen) => now.saturating_sub(seen) <= expiry,
+            //frames without capture metadata can't expire
+            None => true,
+        };
+        self.devices.retain(|_, device| live(device.last_seen()));
+        self.owners.retain(|_, (_, seen)| live(*seen));
+    }
+
+    fn lint_macs(
+        &mut self,
+        info: &FrameInfo,
+        chassis: &ChassisId,
+        now: Option<Duration>,
+    ) -> Vec<LintEntry> {
+        let macs = match find(info, &TlvKey::htip(b"\x03".to_vec())) {
+            Some(ParseData::Mac(macs)) => macs,
+            _ => return vec![],
+        };
+        macs.iter()
+            .filter_map(|mac| {
+                let (owner, seen) = self
+                    .owners
+                    .entry(*mac)
+                    .or_insert_with(|| (chassis.clone(), now));
+                if owner == chassis {
+                    *seen = now.max(*seen);
+                    return None;
+                }
+                Some(
+                    LintEntry::new(Lint::Error(14))
+                        .with_tlv(TlvKey::htip(b"\x03".to_vec()))
+                        .with_extra_info(format!(
+                            "{} already claimed by chassis {}",
+                            mac,
+                            hex(&owner.1)
+                        )),
+                )
+            })
+            .collect()
+    }
+
+    fn lint_identity(info: &FrameInfo, device: &mut Device) -> Vec<LintEntry> {
+        let mut le = vec![];
+        for id in 1..=4 {
+            let key = TlvKey::htip(vec![1, id]);
+            let value = match find(info, &key) {
+                Some(ParseData::Text(value)) => value,
+                _ => continue,
+            };
+            let seen = device.identity.entry(id).or_default();
+            let last = match seen.last() {
+                //first time we see this field
+                None => {
+                    seen.push(value.clone());
+                    continue;
+                }
+                Some(last) if last == value => continue,
+                Some(last) => last.clone(),
+            };
+            let lint = if seen.contains(value) {
+                //move the value to the end, it's the current one again
+                seen.retain(|other| other != value);
+                Lint::Warning(8)
+            } else {
+                Lint::Error(13)
+            };
+            seen.push(value.clone());
+            le.push(
+                LintEntry::new(lint)
+                    .with_tlv(key)
+                    .with_extra_info(format!("{} after {}", value, last)),
+            );
+        }
+        le
+    }
+
+    fn lint_timing(info: &FrameInfo, period: Duration) -> Vec<LintEntry> {
+        let mut le = vec![];
+        if let Some(ttl) = number(info, &TlvKey::new(3, vec![])) {
+            if Duration::from_secs(ttl) < period {
+                le.push(
+                    LintEntry::new(Lint::Warning(9))
+                        .with_tlv(TlvKey::new(3, vec![]))
+                        .with_extra_info(format!(
+                            "TTL {} s, {:.1} s between frames",
+                            ttl,
+                            period.as_secs_f64()
+                        )),
+                );
+            }
+        }
+        let key = TlvKey::htip(vec![1, UPDATE_INTERVAL]);
+        if let Some(interval) = number(info, &key) {
+            let interval = Duration::from_secs(interval);
+            if period > interval * 2 || period * 2 < interval {
+                le.push(
+                    LintEntry::new(Lint::Warning(10))
+                        .with_tlv(key)
+                        .with_extra_info(format!(
+                            "interval {} s, {:.1} s between frames",
+                            interval.as_secs(),
+                            period.as_secs_f64()
+                        )),
+                );
+            }
+        }
+        le
+    }
+}
+
+/// The first parsed information of `key`
+fn find<'i>(info: &'i FrameInfo, key: &TlvKey) -> Option<&'i ParseData> {
+    info.info
+        .iter()
+        .find(|(entry_key, _)| entry_key == key)
+        .map(|(_, data)| data)
+}
+
+/// The first parsed information of `key`, if it is a number
+fn number(info: &FrameInfo, key: &TlvKey) -> Option<u64> {
+    match find(info, key)? {
+        ParseData::U32(number) => Some(u64::from(*number)),
+        ParseData::U64(number) => Some(*number),
+        _ => None,
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Dispatcher, PacketInfo};
+
+    /// A frame of chassis `chassis` with the given HTIP tlvs
+    fn frame(chassis: u8, htip: &[u8]) -> Vec<u8> {
+        let mut frame = vec![0x02, 0x07, 0x04, 0, 0, 0, 0, 0, chassis];
+        frame.extend(b"\x04\x07\x03ABCDEF\x06\x02\x00\x78");
+        frame.extend(htip);
+        frame.extend(b"\x00\x00");
+        frame
+    }
+
+    /// Lint `frames`, each sent at the given second, returning the codes of
+    /// the lints of each frame
+    fn lint(frames: &[(u64, Vec<u8>)]) -> Vec<Vec<String>> {
+        let frames = frames
+            .iter()
+            .map(|(secs, frame)| (*secs, None, frame.clone()))
+            .collect::<Vec<_>>();
+        lint_with(&mut CrossFrameLinter::new(), &frames)
+    }
+
+    /// Lint `frames`, each sent at the given second on the given interface
+    fn lint_with(
+        linter: &mut CrossFrameLinter,
+        frames: &[(u64, Option<&str>, Vec<u8>)],
+    ) -> Vec<Vec<String>> {
+        let mut dsp = Dispatcher::new();
+        frames
+            .iter()
+            .enumerate()
+            .map(|(index, (secs, interface, frame))| {
+                let info = match dsp.parse(frame) {
+                    Ok(info) => info,
+                    Err(_) => panic!("this should parse, check frame!"),
+                };
+                let mut packet = PacketInfo::new(index, Duration::from_secs(*secs), 0, 0);
+                if let Some(interface) = interface {
+                    packet = packet.with_interface(interface.to_string());
+                }
+                linter
+                    .lint(&info.with_packet(packet))
+                    .iter()
+                    .map(|entry| entry.lint.code())
+                    .collect()
+            })
+            .collect()
+    }
+
+    const MAKER_A: &[u8] = b"\xfe\x0c\xe0\x27\x1a\x01\x02\x06AAAAAA";
+    const MAKER_B: &[u8] = b"\xfe\x0c\xe0\x27\x1a\x01\x02\x06BBBBBB";
+
+    #[test]
+    fn conflicting_identity_is_linted() {
+        let result = lint(&[
+            (0, frame(1, MAKER_A)),
+            (100, frame(1, MAKER_A)),
+            (200, frame(1, MAKER_B)),
+            //another device is fine
+            (300, frame(2, MAKER_A)),
+        ]);
+        assert_eq!(result, vec![vec![], vec![], vec!["E13"], vec![]]);
+    }
+
+    #[test]
+    fn flapping_identity_is_linted() {
+        let result = lint(&[
+            (0, frame(1, MAKER_A)),
+            (100, frame(1, MAKER_B)),
+            (200, frame(1, MAKER_A)),
+            (300, frame(1, MAKER_B)),
+        ]);
+        assert_eq!(result, vec![vec![], vec!["E13"], vec!["W8"], vec!["W8"]]);
+    }
+
+    #[test]
+    fn macs_claimed_by_multiple_chassis_are_linted() {
+        let macs = b"\xfe\x0b\xe0\x27\x1a\x03\x01\x00\x11\x22\x33\x44\x55";
+        let result = lint(&[
+            (0, frame(1, macs)),
+            (0, frame(1, macs)),
+            (0, frame(2, macs)),
+        ]);
+        assert_eq!(result, vec![vec![], vec![], vec!["E14"]]);
+    }
+
+    #[test]
+    fn ttl_shorter_than_period_is_linted() {
+        //ttl is 120 seconds
+        let result = lint(&[
+            (0, frame(1, b"")),
+            (60, frame(1, b"")),
+            (300, frame(1, b"")),
+        ]);
+        assert_eq!(result, vec![vec![], vec![], vec!["W9"]]);
+    }
+
+    #[test]
+    fn inconsistent_update_interval_is_linted() {
+        //update interval of 30 seconds
+        let interval = b"\xfe\x0c\xe0\x27\x1a\x01\x17\x06\x00\x00\x00\x00\x00\x1e";
+        let result = lint(&[
+            (0, frame(1, interval)),
+            (30, frame(1, interval)),
+            (40, frame(1, interval)),
+            (110, frame(1, interval)),
+        ]);
+        assert_eq!(result, vec![vec![], vec![], vec!["W10"], vec!["W10"]]);
+    }
+
+    #[test]
+    fn frames_of_other_interfaces_are_not_timed_together() {
+        //update interval of 30 seconds, seen on two interfaces
+        let interval = b"\xfe\x0c\xe0\x27\x1a\x01\x17\x06\x00\x00\x00\x00\x00\x1e";
+        let frames = [
+            (0, Some("eth0"), frame(1, interval)),
+            (0, Some("eth1"), frame(1, interval)),
+            (30, Some("eth0"), frame(1, interval)),
+            (30, Some("eth1"), frame(1, interval)),
+        ];
+        let result = lint_with(&mut CrossFrameLinter::new(), &frames);
+        assert!(result.iter().all(Vec::is_empty), "{:?}", result);
+    }
+
+    #[test]
+    fn unseen_devices_expire() {
+        let macs = b"\xfe\x0b\xe0\x27\x1a\x03\x01\x00\x11\x22\x33\x44\x55";
+        let mut linter = CrossFrameLinter::new().with_expiry(Duration::from_secs(100));
+        let frames = [
+            (0, None, frame(1, &[MAKER_A, macs].concat())),
+            (50, None, frame(2, b"")),
+            //device 1 & its MAC are forgotten by now
+            (150, None, frame(1, MAKER_B)),
+            (160, None, frame(3, macs)),
+        ];
+        let result = lint_with(&mut linter, &frames);
+        assert!(result.iter().all(Vec::is_empty), "{:?}", result);
+        //device 2 expired too
+        assert_eq!(linter.devices.len(), 2);
+        assert_eq!(linter.owners.len(), 1);
+    }
+}
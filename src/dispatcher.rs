@@ -1,12 +1,16 @@
 //TODO fix this `use everything` going on here
 use crate::linters::*;
 use crate::parsers::*;
+use crate::policy::LintPolicy;
+use crate::schema::Schema;
 use crate::subkeys::*;
+use crate::typed::HtipTlv;
 use crate::*;
 use std::cmp::Ordering;
 use std::fmt;
+use std::str::FromStr;
 
-const TTC_OUI: &[u8; 3] = b"\xe0\x27\x1a";
+pub(crate) const TTC_OUI: &[u8; 3] = b"\xe0\x27\x1a";
 
 /// Unique combination of a tlv type and a binary prefix. If a TLV
 /// `matches` a parser key, the registered parser (if any) for that
@@ -102,6 +106,7 @@ pub(crate) fn parse_as_tlv(input: &[u8]) -> Result<TLV, ParsingError> {
 
 ///Parse a frame into a list of tlvs, stop on error
 ///This will never return an empty vector, so it's safe to call last on it
+///Zero bytes after an End TLV are ethernet padding of short frames, not tlvs
 pub(crate) fn parse_frame(frame: &[u8]) -> Result<Vec<TLV>, InvalidFrame> {
     let mut result = vec![];
     let mut input = frame;
@@ -112,8 +117,12 @@ pub(crate) fn parse_frame(frame: &[u8]) -> Result<Vec<TLV>, InvalidFrame> {
                 //calculate the new input
                 assert!(tlv.len() + 2 <= input.len());
                 input = &input[(tlv.len() + 2)..];
+                let end = tlv.tlv_type() == TlvType::End;
                 //save the tlv on the result vector
                 result.push(tlv);
+                if end && input.iter().all(|&byte| byte == 0) {
+                    break;
+                }
             }
             Err(_error) => {
                 return Err(InvalidFrame {
@@ -147,17 +156,45 @@ impl fmt::Display for InvalidFrame<'_> {
 impl<'a> InvalidFrame<'a> {
     pub fn parse(self, dispatcher: &mut Dispatcher) -> FrameInfo<'a> {
         let mut fi = dispatcher.parse_tlvs(self.tlvs);
-        fi.errors.push((
-            TlvKey::new(0, vec![]),
-            ParsingError::InvalidFrame(self.pointer),
-        ));
+        fi.errors
+            .push((None, ParsingError::InvalidFrame(self.pointer)));
         fi
     }
 }
 
+/// How strictly a [Dispatcher] parses frames
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Mode {
+    /// Structural problems are parsing errors, the rest are lints
+    #[default]
+    Normal,
+    /// Every lint of a frame is a parsing error instead
+    /// ([ParsingError::Lint]), e.g. for conformance testing
+    Strict,
+    /// Parsers accept common vendor deviations (sized texts over their
+    /// maximum size, texts padded with NULs), reporting them as
+    /// warning(11), e.g. for field inventory
+    Lenient,
+}
+
+impl FromStr for Mode {
+    type Err = String;
+
+    fn from_str(mode: &str) -> Result<Self, Self::Err> {
+        match mode {
+            "normal" => Ok(Mode::Normal),
+            "strict" => Ok(Mode::Strict),
+            "lenient" => Ok(Mode::Lenient),
+            _ => Err(format!("invalid mode: {}", mode)),
+        }
+    }
+}
+
 pub struct Dispatcher<'a> {
     parsers: Storage<ParserKey, TLV<'a>, Box<dyn Parser>>,
     linters: Vec<Box<dyn Linter>>,
+    policy: LintPolicy,
+    mode: Mode,
 }
 
 impl Default for Dispatcher<'_> {
@@ -186,9 +223,15 @@ impl Dispatcher<'_> {
         Dispatcher {
             parsers: Storage::new(),
             linters: vec![],
+            policy: LintPolicy::new(),
+            mode: Mode::Normal,
         }
     }
 
+    fn context<'s>(&self, data: &'s [u8]) -> Context<'s> {
+        Context::new(data).lenient(self.mode == Mode::Lenient)
+    }
+
     pub(crate) fn parse_tlv<'a, 's>(
         &mut self,
         tlv: &'a TLV<'s>,
@@ -199,9 +242,9 @@ impl Dispatcher<'_> {
             Some(key) => {
                 //skipping data related to the key
                 let skip = key.prefix.len();
-                let parser = self.parsers.get_mut(&key).unwrap();
                 //setup context(take skip into account)
-                let mut context = Context::new(&tlv.value()[skip..]);
+                let mut context = self.context(&tlv.value()[skip..]);
+                let parser = self.parsers.get_mut(&key).unwrap();
                 (key, parser.parse(&mut context))
             }
             None => {
@@ -217,6 +260,32 @@ impl Dispatcher<'_> {
         }
     }
 
+    /// The keys of every registered parser, in order
+    pub fn parser_keys(&self) -> &[ParserKey] {
+        self.parsers.keys()
+    }
+
+    /// Parse a tlv, also returning the number of bytes the parser left unused
+    pub(crate) fn parse_tlv_rest<'s>(
+        &mut self,
+        tlv: &TLV<'s>,
+    ) -> (ParserKey, Result<ParseData, ParsingError<'s>>, usize) {
+        match self.parsers.key_of(tlv) {
+            Some(key) => {
+                let skip = key.prefix.len();
+                let mut context = self.context(&tlv.value()[skip..]);
+                let parser = self.parsers.get_mut(&key).unwrap();
+                let res = parser.parse(&mut context);
+                let rest = context.get().len();
+                (key, res, rest)
+            }
+            None => {
+                let (key, res) = self.parse_tlv(tlv);
+                (key, res, 0)
+            }
+        }
+    }
+
     pub(crate) fn parse_tlv_ex<'a, 's>(
         &mut self,
         tlv: &'a TLV<'s>,
@@ -228,10 +297,18 @@ impl Dispatcher<'_> {
             Some(key) => {
                 //skipping data related to the key
                 let skip = key.prefix.len();
-                let parser = self.parsers.get_mut(&key).unwrap();
                 //setup context(take skip into account)
-                let mut context = Context::new(&tlv.value()[skip..]);
+                let mut context = self.context(&tlv.value()[skip..]);
+                let parser = self.parsers.get_mut(&key).unwrap();
                 let res = (key.clone(), parser.parse(&mut context));
+                //deviations accepted in lenient mode
+                for deviation in context.take_deviations() {
+                    lints.push(
+                        LintEntry::new(Lint::Warning(11))
+                            .with_tlv(key.clone())
+                            .with_extra_info(deviation),
+                    );
+                }
                 //check if context is empty, else issue a lint
                 if !context.get().is_empty() {
                     lints.push(
@@ -256,16 +333,19 @@ impl Dispatcher<'_> {
         }
     }
 
-    pub(crate) fn lint(&self, info: &[InfoEntry]) -> Vec<LintEntry> {
+    pub(crate) fn lint(&self, context: &LintContext) -> Vec<LintEntry> {
         self.linters
             .iter()
-            .flat_map(|linter| linter.lint(info))
+            .flat_map(|linter| linter.lint(context))
             .collect()
     }
 
     /// Parses the given frame and returns relevant [FrameInfo]
     /// If the frame has misconstructed TLVs it returns an [InvalidFrame]
     ///
+    /// The lint policy & the mode are applied to the lints of the frame;
+    /// lints added later on need [Dispatcher::finish()].
+    ///
     /// # Examples
     ///
     /// ```
@@ -287,10 +367,17 @@ impl Dispatcher<'_> {
     fn parse_tlvs<'a>(&mut self, tlvs: Vec<TLV<'a>>) -> FrameInfo<'a> {
         //everything's fine, keep on parsing/linting
         let mut lints = vec![];
-        let (info, errors) = tlvs
+        let results = tlvs
             .iter()
             .map(|tlv| self.parse_tlv_ex(tlv, &mut lints))
-            //split into ok data and parsing errors
+            .collect::<Vec<_>>();
+        //linters see everything, in the original order
+        let context = LintContext::new(&tlvs, &results);
+        lints.append(&mut self.lint(&context));
+
+        //split into ok data and parsing errors
+        let (info, errors) = results
+            .into_iter()
             .partition::<Vec<_>, _>(|(_tlv, res)| res.is_ok());
         //unwrap data
         let info = info
@@ -300,21 +387,94 @@ impl Dispatcher<'_> {
         //unwrap errors
         let errors = errors
             .into_iter()
-            .map(|(tlv, err)| (tlv, err.unwrap_err()))
+            .map(|(tlv, err)| (Some(tlv), err.unwrap_err()))
             .collect::<Vec<_>>();
 
-        lints.append(&mut self.lint(&info));
-        FrameInfo {
+        let mut info = FrameInfo {
             tlvs,
             info,
             errors,
             lints,
+            packet: None,
+        };
+        self.finish(&mut info);
+        info
+    }
+
+    /// Apply the lint policy & the mode to the lints added to `info` after
+    /// [Dispatcher::parse()]: those of [FrameInfo::with_packet()] & of
+    /// cross-frame linting. Suppressions by source only apply once the
+    /// packet is attached. Finishing a frame more than once changes nothing.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rust_htip::dispatcher::Mode;
+    /// use rust_htip::{Dispatcher, Lint, ParsingError};
+    ///
+    /// let mut dispatcher = Dispatcher::new();
+    /// dispatcher.set_mode(Mode::Strict);
+    /// //no End TLV
+    /// let info = dispatcher.parse(b"\x02\x05ABCDE").unwrap();
+    /// assert!(info.lints.is_empty());
+    /// //a frame level lint, about no tlv in particular
+    /// assert_eq!(info.errors[0], (None, ParsingError::Lint(Lint::Error(1))));
+    /// ```
+    pub fn finish(&self, info: &mut FrameInfo) {
+        self.policy.apply(info);
+        if self.mode == Mode::Strict {
+            //every lint left by the policy fails the frame
+            let lints = std::mem::take(&mut info.lints);
+            info.errors.extend(
+                lints
+                    .into_iter()
+                    .map(|entry| (entry.tlv_key, ParsingError::Lint(entry.lint))),
+            );
         }
     }
 
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    /// Parse frames in `mode`; initially [Mode::Normal]
+    pub fn set_mode(&mut self, mode: Mode) {
+        self.mode = mode;
+    }
+
+    /// The lint policy applied to parsed frames; initially every lint is
+    /// reported with its own severity
+    pub fn policy(&self) -> &LintPolicy {
+        &self.policy
+    }
+
+    pub fn set_policy(&mut self, policy: LintPolicy) {
+        self.policy = policy;
+    }
+
     /// Create a new Dispatcher instance
     pub fn new() -> Self {
         let mut instance = Dispatcher::empty();
+        instance.register_builtins();
+        instance
+    }
+
+    /// Also parse the tlvs defined by `schema`, replacing the built-in
+    /// parsers of the same keys
+    pub fn add_schema(&mut self, schema: Schema) {
+        for tlv in schema.tlvs() {
+            self.parsers.insert(tlv.key.clone(), tlv.parser());
+        }
+        self.linters.extend(schema.linter());
+    }
+
+    /// Also parse the tlvs of the Rust type `T`, see [HtipTlv]
+    pub fn register<T: HtipTlv>(&mut self) {
+        self.add_schema(T::schema());
+    }
+
+    fn register_builtins(&mut self) {
+        let instance = self;
         instance.add_parser(TlvType::from(0u8), b"".to_vec(), Box::new(NoData));
         instance.add_parser(TlvType::from(1u8), b"".to_vec(), Box::new(TypedData::new()));
         instance.add_parser(TlvType::from(2u8), b"".to_vec(), Box::new(TypedData::new()));
@@ -392,7 +552,8 @@ impl Dispatcher<'_> {
         instance.linters.push(Box::new(CheckEndTlv));
         instance.linters.push(Box::new(InvalidChars::new()));
         instance.linters.push(Box::new(TLV1Linter));
-        instance
+        instance.linters.push(Box::new(TlvOrder));
+        instance.linters.push(Box::new(HtipMachineInfo));
     }
 }
 #[cfg(test)]
@@ -474,6 +635,19 @@ mod parse_tests {
         assert_eq!(tlv.value(), expected_value);
     }
 
+    #[test]
+    fn parse_frame_skips_padding_after_end() {
+        //even & odd number of padding bytes
+        for padding in &[&b"\x00\x00\x00\x00"[..], b"\x00\x00\x00"] {
+            let frame = [&b"\x02\x04abcd\x00\x00"[..], padding].concat();
+            let result = parse_frame(&frame).expect("this should parse cleanly!");
+            assert_eq!(result.len(), 2);
+        }
+        //anything else is data after the End TLV
+        let result = parse_frame(b"\x02\x04abcd\x00\x00\x00\x00\x08\x01x").unwrap();
+        assert_eq!(result.len(), 4);
+    }
+
     #[test]
     fn parse_frame_3tlvs_last_one_error() {
         let frame = b"\x02\x03123\x04\x0512345\x03\x1ftoo short";
@@ -496,6 +670,7 @@ mod parse_tests {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::time::Duration;
 
     #[test]
     fn api_test() {
@@ -575,8 +750,12 @@ mod tests {
 
     #[test]
     fn parse_detects_trailing_characters() {
-        let frame = b"\xfe\x19\xe0\x27\x1a\x01\x01\x09123456789characters\
+        //mandatory chassis id, port id & ttl first, then htip info 1 to 4
+        let frame = b"\x02\x07\x04ABCDEF\x04\x07\x03ABCDEF\x06\x02\x00\x78\
+            \xfe\x19\xe0\x27\x1a\x01\x01\x09123456789characters\
             \xfe\x11\xe0\x27\x1a\x01\x02\x06CAFEBEextra\
+            \xfe\x07\xe0\x27\x1a\x01\x03\x01M\
+            \xfe\x07\xe0\x27\x1a\x01\x04\x01N\
             \x00\x00";
         let mut dsp = Dispatcher::new();
         let results = dsp.parse(frame).expect("this should parse, check frame!");
@@ -586,10 +765,25 @@ mod tests {
         assert_eq!(results.lints.len(), 2);
     }
 
+    #[test]
+    fn padded_frames_have_no_data_after_end() {
+        //a short frame padded to the ethernet minimum
+        let frame = [
+            &b"\x02\x07\x04ABCDEF\x04\x07\x03ABCDEF\x06\x02\x00\x78\x00\x00"[..],
+            &[0; 17],
+        ]
+        .concat();
+        let mut dsp = Dispatcher::new();
+        let results = dsp.parse(&frame).expect("this should parse, check frame!");
+        assert!(results.errors.is_empty());
+        assert!(results.lints.is_empty(), "{:?}", results.lints);
+    }
+
     #[test]
     fn parse_detects_unknown_tlvs() {
-        //\xf0 is unknown to us
-        let frame = b"\xf0\x100123456789ABCDEF\
+        //mandatory chassis id, port id & ttl first, \xf0 is unknown to us
+        let frame = b"\x02\x07\x04ABCDEF\x04\x07\x03ABCDEF\x06\x02\x00\x78\
+            \xf0\x100123456789ABCDEF\
             \x00\x00";
         let mut dsp = Dispatcher::new();
         let results = dsp.parse(frame).expect("this should parse, check frame!");
@@ -597,9 +791,77 @@ mod tests {
         assert!(results.errors.is_empty());
         assert_eq!(results.lints[0].lint, Lint::Warning(3));
         assert_eq!(results.lints.len(), 1);
-        match &results.info[0].1 {
+        match &results.info[3].1 {
             ParseData::Binary(bin) => assert_eq!(bin, b"0123456789ABCDEF"),
             _ => panic!("this should be a string!"),
         }
     }
+
+    //a 32 character model name (max 31) & a NUL padded port description
+    const DEVIATING: &[u8] = b"\x02\x07\x04ABCDEF\x04\x07\x03ABCDEF\x06\x02\x00\x78\
+        \xfe\x26\xe0\x27\x1a\x01\x03\x20ABCDEFGHIJKLMNOPQRSTUVWXYZ012345\
+        \x08\x06port\x00\x00\
+        \x00\x00";
+
+    #[test]
+    fn normal_mode_rejects_deviations() {
+        let mut dsp = Dispatcher::new();
+        let results = dsp
+            .parse(DEVIATING)
+            .expect("this should parse, check frame!");
+        assert_eq!(results.errors[0].1, ParsingError::UnexpectedLength(32));
+        assert!(results
+            .info
+            .iter()
+            .any(|(_, data)| *data == ParseData::Text("port\0\0".to_string())));
+    }
+
+    #[test]
+    fn lenient_mode_accepts_deviations() {
+        let mut dsp = Dispatcher::new();
+        dsp.set_mode(Mode::Lenient);
+        let results = dsp
+            .parse(DEVIATING)
+            .expect("this should parse, check frame!");
+        assert!(results.errors.is_empty());
+        assert_eq!(
+            results.info[3].1,
+            ParseData::Text("ABCDEFGHIJKLMNOPQRSTUVWXYZ012345".to_string())
+        );
+        assert_eq!(results.info[4].1, ParseData::Text("port".to_string()));
+        let deviations = results
+            .lints
+            .iter()
+            .filter(|entry| entry.lint == Lint::Warning(11))
+            .filter_map(|entry| entry.extra_info.as_deref())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            deviations,
+            vec!["text size 32 over maximum 31", "2 trailing NUL characters"]
+        );
+    }
+
+    #[test]
+    fn strict_mode_fails_on_lints() {
+        //no End TLV
+        let frame = b"\x02\x07\x04ABCDEF\x04\x07\x03ABCDEF\x06\x02\x00\x78";
+        let mut dsp = Dispatcher::new();
+        dsp.set_mode(Mode::Strict);
+        let results = dsp.parse(frame).expect("this should parse, check frame!");
+        assert!(results.lints.is_empty());
+        assert!(results
+            .errors
+            .iter()
+            .any(|(_, error)| *error == ParsingError::Lint(Lint::Error(1))));
+        assert_eq!("strict".parse(), Ok(Mode::Strict));
+
+        //lints of the packet fail the frame too
+        let packet = PacketInfo::new(0, Duration::from_secs(0), 8, 100);
+        let mut results = dsp.parse(frame).ok().unwrap().with_packet(packet);
+        dsp.finish(&mut results);
+        assert!(results
+            .errors
+            .iter()
+            .any(|(_, error)| *error == ParsingError::Lint(Lint::Warning(4))));
+    }
 }
@@ -0,0 +1,176 @@
+use crate::{FrameInfo, Lint, ParseData, TlvKey};
+use macaddr::MacAddr6;
+
+/// Selects frames by source address, maker code, lints and parsing errors.
+///
+/// An empty filter selects every frame; each configured criterion must match
+/// for a frame to be selected.
+///
+/// # Examples
+///
+/// ```
+/// use rust_htip::filter::FrameFilter;
+/// use rust_htip::Lint;
+///
+/// //frames of maker "ABCDEF" that either lack an End TLV or have parsing errors
+/// let filter = FrameFilter::new()
+///     .maker_code("ABCDEF")
+///     .lint(Lint::Error(1))
+///     .with_errors();
+/// ```
+#[derive(Debug, Default)]
+pub struct FrameFilter {
+    sources: Vec<MacAddr6>,
+    maker_codes: Vec<String>,
+    lints: Vec<Lint>,
+    with_errors: bool,
+}
+
+impl FrameFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Select frames sent from `source`; may be repeated to select any of them
+    pub fn source(mut self, source: MacAddr6) -> Self {
+        self.sources.push(source);
+        self
+    }
+
+    /// Select frames advertising `code` as their maker code (subtype 1, info 2),
+    /// ignoring ascii case; may be repeated to select any of them
+    pub fn maker_code(mut self, code: &str) -> Self {
+        self.maker_codes.push(code.to_string());
+        self
+    }
+
+    /// Select broken frames: frames with the given lint, or with a parsing error
+    /// if [FrameFilter::with_errors()] is also set. May be repeated.
+    pub fn lint(mut self, lint: Lint) -> Self {
+        self.lints.push(lint);
+        self
+    }
+
+    /// Select broken frames: frames with parsing errors, or with one of the
+    /// lints given with [FrameFilter::lint()]
+    pub fn with_errors(self) -> Self {
+        FrameFilter {
+            with_errors: true,
+            ..self
+        }
+    }
+
+    /// Checks if the frame is selected by this filter
+    pub fn matches(&self, info: &FrameInfo) -> bool {
+        self.matches_source(info) && self.matches_maker_code(info) && self.matches_broken(info)
+    }
+
+    fn matches_source(&self, info: &FrameInfo) -> bool {
+        if self.sources.is_empty() {
+            return true;
+        }
+        info.packet
+            .as_ref()
+            .and_then(|packet| packet.source)
+            .is_some_and(|source| self.sources.contains(&source))
+    }
+
+    fn matches_maker_code(&self, info: &FrameInfo) -> bool {
+        if self.maker_codes.is_empty() {
+            return true;
+        }
+        let maker_code_key = TlvKey::htip(b"\x01\x02".to_vec());
+        info.info.iter().any(|(key, data)| match data {
+            ParseData::Text(code) if *key == maker_code_key => self
+                .maker_codes
+                .iter()
+                .any(|wanted| wanted.eq_ignore_ascii_case(code)),
+            _ => false,
+        })
+    }
+
+    fn matches_broken(&self, info: &FrameInfo) -> bool {
+        if self.lints.is_empty() && !self.with_errors {
+            return true;
+        }
+        let has_lint = info
+            .lints
+            .iter()
+            .any(|entry| self.lints.contains(&entry.lint));
+        has_lint || (self.with_errors && !info.errors.is_empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Dispatcher, PacketInfo};
+    use std::time::Duration;
+
+    //maker code ABCDEF, no End TLV
+    const FRAME: &[u8] = b"\xfe\x0c\xe0\x27\x1a\x01\x02\x06ABCDEF";
+
+    fn packet(source: [u8; 6]) -> PacketInfo {
+        PacketInfo::new(0, Duration::default(), 0, 0).with_source(MacAddr6::from(source))
+    }
+
+    #[test]
+    fn empty_filter_matches_everything() {
+        let mut dsp = Dispatcher::new();
+        let info = dsp.parse(FRAME).unwrap_or_else(|_| panic!("check frame!"));
+        assert!(FrameFilter::new().matches(&info));
+    }
+
+    #[test]
+    fn filter_by_source() {
+        let mut dsp = Dispatcher::new();
+        let info = dsp
+            .parse(FRAME)
+            .unwrap_or_else(|_| panic!("check frame!"))
+            .with_packet(packet([1, 2, 3, 4, 5, 6]));
+        let ours = MacAddr6::from([1, 2, 3, 4, 5, 6]);
+        let theirs = MacAddr6::from([6, 5, 4, 3, 2, 1]);
+        assert!(FrameFilter::new().source(ours).matches(&info));
+        assert!(FrameFilter::new()
+            .source(theirs)
+            .source(ours)
+            .matches(&info));
+        assert!(!FrameFilter::new().source(theirs).matches(&info));
+    }
+
+    #[test]
+    fn filter_by_source_without_packet_info_fails() {
+        let mut dsp = Dispatcher::new();
+        let info = dsp.parse(FRAME).unwrap_or_else(|_| panic!("check frame!"));
+        let source = MacAddr6::from([1, 2, 3, 4, 5, 6]);
+        assert!(!FrameFilter::new().source(source).matches(&info));
+    }
+
+    #[test]
+    fn filter_by_maker_code() {
+        let mut dsp = Dispatcher::new();
+        let info = dsp.parse(FRAME).unwrap_or_else(|_| panic!("check frame!"));
+        assert!(FrameFilter::new().maker_code("ABCDEF").matches(&info));
+        assert!(FrameFilter::new().maker_code("abcdef").matches(&info));
+        assert!(!FrameFilter::new().maker_code("012345").matches(&info));
+    }
+
+    #[test]
+    fn filter_by_lints_and_errors() {
+        let mut dsp = Dispatcher::new();
+        let info = dsp.parse(FRAME).unwrap_or_else(|_| panic!("check frame!"));
+        assert!(FrameFilter::new().lint(Lint::Error(1)).matches(&info));
+        assert!(!FrameFilter::new().lint(Lint::Warning(1)).matches(&info));
+        assert!(!FrameFilter::new().with_errors().matches(&info));
+        assert!(FrameFilter::new()
+            .lint(Lint::Error(1))
+            .with_errors()
+            .matches(&info));
+
+        let info = match dsp.parse(b"\x02\x05ABC") {
+            Err(bad_frame) => bad_frame.parse(&mut dsp),
+            Ok(_) => panic!("a truncated frame should not parse!"),
+        };
+        assert!(FrameFilter::new().with_errors().matches(&info));
+    }
+}
@@ -0,0 +1,336 @@
+use crate::linters::InvalidChars;
+use crate::{Dispatcher, FrameInfo, Lint, ParseData, ParsingError, TlvType, TLV};
+use std::fmt;
+
+/// A single change made by a [FrameFixer]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fix {
+    /// The lint that is fixed
+    pub lint: Lint,
+    /// Index of the changed tlv in the original frame, if any
+    pub tlv: Option<usize>,
+    pub description: String,
+}
+
+impl fmt::Display for Fix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.tlv {
+            Some(index) => write!(
+                f,
+                "{}, tlv #{}: {}",
+                self.lint.code(),
+                index,
+                self.description
+            ),
+            None => write!(f, "{}: {}", self.lint.code(), self.description),
+        }
+    }
+}
+
+/// A repaired frame, along with the changes that were made
+#[derive(Debug, Clone)]
+pub struct FixedFrame {
+    pub frame: Vec<u8>,
+    pub fixes: Vec<Fix>,
+}
+
+/// Repairs trivially fixable lints of a frame:
+/// * missing End TLV (error(1)): append one
+/// * data after the End TLV (error(9)): drop it
+/// * trailing bytes after a parsed value (warning(2)): trim them
+/// * invalid characters (warning(1)): upper case them if allowed, else
+///   replace them (see [FrameFixer::with_replacement()]) or drop them
+///
+/// Declared sizes (tlv lengths & text sizes) always match the repaired
+/// values, and texts are left alone if the repaired text would no longer
+/// parse, e.g. a maker code shorter than 6 characters. Only the lints of
+/// the frame are fixed (strict mode errors included), so lints disabled by
+/// a [LintPolicy](crate::policy::LintPolicy) are left alone.
+///
+/// # Examples
+///
+/// ```
+/// use rust_htip::fixer::FrameFixer;
+/// use rust_htip::Dispatcher;
+///
+/// let mut dispatcher = Dispatcher::new();
+/// //no End TLV
+/// let frame = b"\x02\x07\x04ABCDEF\x04\x07\x03ABCDEF\x06\x02\x00\x78";
+/// let info = dispatcher.parse(frame).ok().unwrap();
+/// let fixed = FrameFixer::new().fix(&mut dispatcher, &info);
+/// assert!(fixed.frame.ends_with(b"\x00\x00"));
+/// assert_eq!(fixed.fixes[0].to_string(), "E1: appended End TLV");
+/// ```
+pub struct FrameFixer {
+    replacement: Option<char>,
+    invalid_chars: InvalidChars,
+}
+
+impl Default for FrameFixer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FrameFixer {
+    /// A fixer that drops invalid characters
+    pub fn new() -> Self {
+        FrameFixer {
+            replacement: None,
+            invalid_chars: InvalidChars::new(),
+        }
+    }
+
+    /// Replace invalid characters with `replacement`, where it is allowed
+    pub fn with_replacement(self, replacement: char) -> Self {
+        FrameFixer {
+            replacement: Some(replacement),
+            ..self
+        }
+    }
+
+    /// Repair the frame of `info`, which was parsed by `dispatcher`
+    pub fn fix(&self, dispatcher: &mut Dispatcher, info: &FrameInfo) -> FixedFrame {
+        let has = |lint| {
+            info.lints.iter().any(|entry| entry.lint == lint)
+                || info
+                    .errors
+                    .iter()
+                    .any(|(_, error)| *error == ParsingError::Lint(lint))
+        };
+        let mut fixes = vec![];
+        let mut frame = vec![];
+
+        let end = info
+            .tlvs
+            .iter()
+            .position(|tlv| tlv.tlv_type() == TlvType::End);
+        for (index, tlv) in info.tlvs.iter().enumerate() {
+            if matches!(end, Some(end) if index > end) && has(Lint::Error(9)) {
+                fixes.push(Fix {
+                    lint: Lint::Error(9),
+                    tlv: Some(index),
+                    description: "dropped tlv after End TLV".to_string(),
+                });
+                continue;
+            }
+            let value = self.fix_value(dispatcher, tlv, index, &has, &mut fixes);
+            let bytes = TLV::new(tlv.tlv_type(), value.len(), &value)
+                .to_bytes()
+                .or_else(|_| {
+                    //replacements grew the value past its tlv, keep the original
+                    fixes.retain(|fix| fix.tlv != Some(index));
+                    tlv.to_bytes()
+                });
+            frame.extend(bytes.expect("parsed tlvs fit their length"));
+        }
+
+        if end.is_none() && has(Lint::Error(1)) {
+            frame.extend(
+                TLV::new(TlvType::End, 0, b"")
+                    .to_bytes()
+                    .unwrap_or_default(),
+            );
+            fixes.push(Fix {
+                lint: Lint::Error(1),
+                tlv: None,
+                description: "appended End TLV".to_string(),
+            });
+        }
+        FixedFrame { frame, fixes }
+    }
+
+    fn fix_value(
+        &self,
+        dispatcher: &mut Dispatcher,
+        tlv: &TLV,
+        index: usize,
+        has: &dyn Fn(Lint) -> bool,
+        fixes: &mut Vec<Fix>,
+    ) -> Vec<u8> {
+        let (key, result, trailing) = dispatcher.parse_tlv_rest(tlv);
+        let mut value = tlv.value().to_vec();
+
+        if trailing > 0 && has(Lint::Warning(2)) {
+            value.truncate(value.len() - trailing);
+            fixes.push(Fix {
+                lint: Lint::Warning(2),
+                tlv: Some(index),
+                description: format!("trimmed {} trailing bytes", trailing),
+            });
+        }
+
+        let (allowed, text) = match (self.invalid_chars.allowed(&key), &result) {
+            (Some(allowed), Ok(ParseData::Text(text))) if has(Lint::Warning(1)) => (allowed, text),
+            _ => return value,
+        };
+        let fixed = self.fix_text(allowed, text);
+        if fixed == *text {
+            return value;
+        }
+        //custom tlvs hold a sized text after their prefix, others only text
+        let start = match tlv.tlv_type() {
+            TlvType::Custom => key.prefix.len() + 1,
+            _ => 0,
+        };
+        let mut fixed_value = value[..start].to_vec();
+        if start > 0 {
+            fixed_value[start - 1] = fixed.len() as u8;
+        }
+        fixed_value.extend(fixed.as_bytes());
+        fixed_value.extend(&value[start + text.len()..]);
+        //e.g. dropped characters leave a fixed size text too short
+        let fixed_tlv = TLV::new(tlv.tlv_type(), fixed_value.len(), &fixed_value);
+        if dispatcher.parse_tlv(&fixed_tlv).1.is_err() {
+            return value;
+        }
+        fixes.push(Fix {
+            lint: Lint::Warning(1),
+            tlv: Some(index),
+            description: format!("replaced text {:?} with {:?}", text, fixed),
+        });
+        fixed_value
+    }
+
+    fn fix_text(&self, allowed: &str, text: &str) -> String {
+        let replacement = self
+            .replacement
+            .filter(|replacement| allowed.contains(*replacement));
+        text.chars()
+            .filter_map(|c| {
+                let upper = c.to_ascii_uppercase();
+                if allowed.contains(c) {
+                    Some(c)
+                } else if allowed.contains(upper) {
+                    Some(upper)
+                } else {
+                    replacement
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MANDATORY: &[u8] = b"\x02\x07\x04ABCDEF\x04\x07\x03ABCDEF\x06\x02\x00\x78";
+
+    fn fix(fixer: &FrameFixer, tlvs: &[u8]) -> (FixedFrame, Vec<Lint>) {
+        let frame = [MANDATORY, tlvs].concat();
+        let mut dsp = Dispatcher::new();
+        let info = match dsp.parse(&frame) {
+            Ok(info) => info,
+            Err(_) => panic!("this should parse, check frame!"),
+        };
+        let fixed = fixer.fix(&mut dsp, &info);
+        //lints of the repaired frame
+        let lints = match dsp.parse(&fixed.frame) {
+            Ok(info) => info.lints.iter().map(|entry| entry.lint).collect(),
+            Err(_) => panic!("the fixed frame should parse!"),
+        };
+        (fixed, lints)
+    }
+
+    #[test]
+    fn tlvs_are_encoded() {
+        let tlv = TLV::new(TlvType::Custom, 3, b"abc");
+        assert_eq!(tlv.to_bytes(), Ok(b"\xfe\x03abc".to_vec()));
+        let value = [0u8; 300];
+        let tlv = TLV::new(TlvType::PortID, value.len(), &value);
+        assert_eq!(&tlv.to_bytes().unwrap()[..2], b"\x05\x2c");
+        let value = [0u8; 512];
+        let tlv = TLV::new(TlvType::PortID, value.len(), &value);
+        assert_eq!(
+            tlv.to_bytes(),
+            Err("value of 512 bytes over 511".to_string())
+        );
+    }
+
+    #[test]
+    fn frames_without_lints_are_unchanged() {
+        let (fixed, _) = fix(&FrameFixer::new(), b"\x00\x00");
+        assert!(fixed.fixes.is_empty());
+        assert_eq!(fixed.frame, [MANDATORY, b"\x00\x00"].concat());
+    }
+
+    #[test]
+    fn end_tlv_is_appended_and_data_after_it_dropped() {
+        let (fixed, lints) = fix(&FrameFixer::new(), b"");
+        assert_eq!(fixed.fixes.len(), 1);
+        assert!(!lints.contains(&Lint::Error(1)));
+
+        let (fixed, lints) = fix(&FrameFixer::new(), b"\x00\x00\x08\x01x");
+        assert_eq!(fixed.fixes[0].tlv, Some(4));
+        assert_eq!(fixed.frame, [MANDATORY, b"\x00\x00"].concat());
+        assert!(!lints.contains(&Lint::Error(9)));
+    }
+
+    #[test]
+    fn padding_is_not_fixed() {
+        let (fixed, _) = fix(&FrameFixer::new(), b"\x00\x00\x00\x00\x00");
+        assert!(fixed.fixes.is_empty());
+    }
+
+    #[test]
+    fn trailing_bytes_are_trimmed() {
+        let (fixed, lints) = fix(
+            &FrameFixer::new(),
+            b"\xfe\x0f\xe0\x27\x1a\x01\x02\x06CAFEBEext\x00\x00",
+        );
+        assert_eq!(
+            fixed.fixes[0].to_string(),
+            "W2, tlv #3: trimmed 3 trailing bytes"
+        );
+        assert!(!lints.contains(&Lint::Warning(2)));
+    }
+
+    #[test]
+    fn invalid_characters_are_fixed() {
+        //lower case maker code & a port description with a tab
+        let tlvs = b"\xfe\x0c\xe0\x27\x1a\x01\x02\x06cafebe\x08\x04a\tbc\x00\x00";
+        let (fixed, lints) = fix(&FrameFixer::new(), tlvs);
+        assert_eq!(fixed.fixes.len(), 2);
+        assert!(fixed.frame.windows(6).any(|window| window == b"CAFEBE"));
+        assert!(fixed
+            .frame
+            .windows(5)
+            .any(|window| window == b"\x08\x03abc"));
+        assert!(!lints.contains(&Lint::Warning(1)));
+
+        let (fixed, _) = fix(&FrameFixer::new().with_replacement(' '), tlvs);
+        assert!(fixed
+            .frame
+            .windows(6)
+            .any(|window| window == b"\x08\x04a bc"));
+    }
+
+    #[test]
+    fn texts_that_would_not_parse_are_left_alone() {
+        //a maker code of 5 valid characters once the invalid one is dropped
+        let tlvs = b"\xfe\x0c\xe0\x27\x1a\x01\x02\x06CAFEB~\x00\x00";
+        let (fixed, lints) = fix(&FrameFixer::new(), tlvs);
+        assert!(fixed.fixes.is_empty());
+        assert_eq!(fixed.frame, [MANDATORY, &tlvs[..]].concat());
+        assert!(lints.contains(&Lint::Warning(1)));
+
+        let (fixed, lints) = fix(&FrameFixer::new().with_replacement('0'), tlvs);
+        assert_eq!(fixed.fixes.len(), 1);
+        assert!(!lints.contains(&Lint::Warning(1)));
+    }
+
+    #[test]
+    fn sized_texts_keep_their_size_consistent() {
+        //model number (info ID 4) with invalid characters & trailing bytes
+        let tlvs = b"\xfe\x0c\xe0\x27\x1a\x01\x04\x04A~B~xx\x00\x00";
+        let (fixed, lints) = fix(&FrameFixer::new(), tlvs);
+        assert_eq!(fixed.fixes.len(), 2);
+        assert!(fixed
+            .frame
+            .windows(10)
+            .any(|window| window == b"\xfe\x08\xe0\x27\x1a\x01\x04\x02AB"));
+        assert!(!lints.contains(&Lint::Warning(1)));
+        assert!(!lints.contains(&Lint::Warning(2)));
+    }
+}
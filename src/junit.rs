@@ -0,0 +1,269 @@
+use crate::conformance::{ConformanceReport, Verdict};
+use crate::{FrameInfo, Severity};
+
+/// A single JUnit test case
+#[derive(Debug, Clone)]
+struct TestCase {
+    name: String,
+    /// Message & details of the failure, if the case failed
+    failure: Option<(String, String)>,
+    /// Details of a passed case, e.g. its warnings
+    output: Option<String>,
+    /// The case was not evaluated
+    skipped: bool,
+}
+
+/// A JUnit XML test suite of lint results, for CI systems. Test cases are
+/// either one per frame ([JUnitReport::add_frame()]), failing on parsing
+/// errors & lints reported as errors, or one per lint rule
+/// ([JUnitReport::from_conformance()]), failing on a failed requirement.
+/// Warnings are kept as the output of passed cases.
+///
+/// # Examples
+///
+/// ```
+/// use rust_htip::junit::JUnitReport;
+/// use rust_htip::Dispatcher;
+///
+/// let mut dispatcher = Dispatcher::new();
+/// let mut report = JUnitReport::new("golden");
+/// //no End TLV
+/// let frame = b"\x02\x07\x04ABCDEF\x04\x07\x03ABCDEF\x06\x02\x00\x78";
+/// report.add_frame(&dispatcher.parse(frame).ok().unwrap());
+/// assert_eq!(report.failures(), 1);
+/// assert!(report.to_xml().contains("failures=\"1\""));
+/// ```
+#[derive(Debug, Clone)]
+pub struct JUnitReport {
+    name: String,
+    /// The capture file frames are added from, if any
+    capture: Option<String>,
+    cases: Vec<TestCase>,
+}
+
+impl JUnitReport {
+    /// An empty test suite called `name`
+    pub fn new(name: &str) -> Self {
+        JUnitReport {
+            name: name.to_string(),
+            capture: None,
+            cases: vec![],
+        }
+    }
+
+    /// Set the capture file the next frames are added from, which prefixes
+    /// their test case names, keeping them unique across files
+    pub fn set_capture(&mut self, capture: &str) {
+        self.capture = Some(capture.to_string());
+    }
+
+    /// A test suite with a test case for each requirement of `report`
+    pub fn from_conformance(name: &str, report: &ConformanceReport) -> Self {
+        let cases = report
+            .requirements
+            .iter()
+            .map(|requirement| {
+                let name = match requirement.lint {
+                    Some(lint) => format!("{} {}", lint.code(), requirement.id),
+                    None => requirement.id.to_string(),
+                };
+                let evidence = requirement
+                    .evidence
+                    .iter()
+                    .map(|index| format!("frame {}", index))
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                let details = match requirement.reference {
+                    Some(reference) => format!("{}\n{}", reference, evidence),
+                    None => evidence,
+                };
+                let (failure, output) = match requirement.verdict {
+                    Verdict::NotEvaluated | Verdict::Pass => (None, None),
+                    Verdict::Warning => (None, Some(details)),
+                    Verdict::Fail => (Some((requirement.description.to_string(), details)), None),
+                };
+                TestCase {
+                    name,
+                    failure,
+                    output,
+                    skipped: requirement.verdict == Verdict::NotEvaluated,
+                }
+            })
+            .collect();
+        JUnitReport {
+            name: name.to_string(),
+            capture: None,
+            cases,
+        }
+    }
+
+    /// Add a test case for `info`, named after its capture file & packet if
+    /// any
+    pub fn add_frame(&mut self, info: &FrameInfo) {
+        let frame = match &info.packet {
+            Some(packet) => format!("frame {}", packet.index),
+            None => format!("frame {}", self.cases.len()),
+        };
+        let name = match &self.capture {
+            Some(capture) => format!("{}: {}", capture, frame),
+            None => frame,
+        };
+        let annotations = info.annotations().join("\n");
+        let errors = info.errors.len()
+            + info
+                .lints
+                .iter()
+                .filter(|entry| entry.severity == Severity::Error)
+                .count();
+        let case = if errors > 0 {
+            TestCase {
+                name,
+                failure: Some((format!("{} errors", errors), annotations)),
+                output: None,
+                skipped: false,
+            }
+        } else {
+            TestCase {
+                name,
+                failure: None,
+                output: Some(annotations).filter(|output| !output.is_empty()),
+                skipped: false,
+            }
+        };
+        self.cases.push(case);
+    }
+
+    /// The number of failed test cases
+    pub fn failures(&self) -> usize {
+        self.cases
+            .iter()
+            .filter(|case| case.failure.is_some())
+            .count()
+    }
+
+    pub fn to_xml(&self) -> String {
+        let failures = self.failures();
+        let skipped = self.cases.iter().filter(|case| case.skipped).count();
+        let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        out += &format!(
+            "<testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"0\" skipped=\"{}\">\n",
+            escape(&self.name),
+            self.cases.len(),
+            failures,
+            skipped
+        );
+        for case in &self.cases {
+            out += &format!(
+                "  <testcase name=\"{}\" classname=\"{}\"",
+                escape(&case.name),
+                escape(&self.name)
+            );
+            match (&case.failure, &case.output) {
+                (None, None) if case.skipped => out += ">\n    <skipped/>\n  </testcase>\n",
+                (None, None) => out += "/>\n",
+                (Some((message, details)), _) => {
+                    out += &format!(
+                        ">\n    <failure message=\"{}\">{}</failure>\n  </testcase>\n",
+                        escape(message),
+                        escape(details)
+                    )
+                }
+                (None, Some(output)) => {
+                    out += &format!(
+                        ">\n    <system-out>{}</system-out>\n  </testcase>\n",
+                        escape(output)
+                    )
+                }
+            }
+        }
+        out += "</testsuite>\n";
+        out
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '&' => "&amp;".to_string(),
+            '<' => "&lt;".to_string(),
+            '>' => "&gt;".to_string(),
+            '"' => "&quot;".to_string(),
+            '\'' => "&apos;".to_string(),
+            //control characters are not allowed in xml 1.0
+            c if c.is_control() && c != '\n' && c != '\t' => '\u{fffd}'.to_string(),
+            c => c.to_string(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conformance::ConformanceTest;
+    use crate::{Dispatcher, PacketInfo};
+    use std::time::Duration;
+
+    const FRAME: &[u8] = b"\x02\x07\x04ABCDEF\x04\x07\x03ABCDEF\x06\x02\x00\x78";
+
+    #[test]
+    fn frames_are_test_cases() {
+        let mut dsp = Dispatcher::new();
+        let mut report = JUnitReport::new("capture <1>");
+        report.add_frame(&dsp.parse(FRAME).ok().unwrap());
+        //TTL value too short
+        let bad = b"\x02\x07\x04ABCDEF\x04\x07\x03ABCDEF\x06\x01\x00\x00\x00";
+        match dsp.parse(bad) {
+            Ok(info) => report.add_frame(&info),
+            Err(err) => report.add_frame(&err.parse(&mut dsp)),
+        }
+        let xml = report.to_xml();
+        assert!(xml.contains(
+            "<testsuite name=\"capture &lt;1&gt;\" tests=\"2\" failures=\"2\" errors=\"0\" skipped=\"0\">"
+        ));
+        assert!(xml.contains("<testcase name=\"frame 1\" classname=\"capture &lt;1&gt;\">"));
+        assert!(xml.contains("No End TLV"));
+        assert!(xml.ends_with("</testsuite>\n"));
+    }
+
+    #[test]
+    fn frames_are_named_after_their_capture() {
+        let mut dsp = Dispatcher::new();
+        let mut report = JUnitReport::new("golden");
+        let mut info = dsp.parse(FRAME).ok().unwrap();
+        info.packet = Some(PacketInfo::new(1, Duration::from_secs(0), 19, 19));
+        report.set_capture("a.pcap");
+        report.add_frame(&info);
+        report.set_capture("b.pcapng");
+        report.add_frame(&info);
+        let xml = report.to_xml();
+        assert!(xml.contains("<testcase name=\"a.pcap: frame 1\" classname=\"golden\">"));
+        assert!(xml.contains("<testcase name=\"b.pcapng: frame 1\" classname=\"golden\">"));
+        assert_eq!(report.failures(), 2);
+    }
+
+    #[test]
+    fn rules_are_test_cases() {
+        let mut dsp = Dispatcher::new();
+        let mut test = ConformanceTest::new();
+        test.add(&dsp.parse(FRAME).ok().unwrap());
+        let report = JUnitReport::from_conformance("device", &test.report());
+        let xml = report.to_xml();
+        assert!(xml.contains("<testcase name=\"frames\" classname=\"device\"/>"));
+        assert!(xml.contains(
+            "<testcase name=\"W9 ttl-shorter-than-period\" classname=\"device\">\n    \
+            <skipped/>"
+        ));
+        assert!(xml.contains(
+            "<testcase name=\"E1 missing-end-tlv\" classname=\"device\">\n    \
+            <failure message=\"No End TLV\">"
+        ));
+    }
+
+    #[test]
+    fn text_is_escaped() {
+        assert_eq!(
+            escape("a<b>&\"'\u{1}\n"),
+            "a&lt;b&gt;&amp;&quot;&apos;\u{fffd}\n"
+        );
+    }
+}
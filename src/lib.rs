@@ -14,27 +14,66 @@
 
 #![deny(broken_intra_doc_links)]
 //TODO figure out proper visibilities
+/// Ethernet framing & pcap metadata of the packets carrying HTIP frames
+pub mod capture;
+/// Combinators to build parsers of new layouts out of other parsers, e.g. a
+/// [Record](combinators::Record) of fields. Errors name the failed part.
+pub mod combinators;
+/// Pass/fail conformance reports of HTIP agents
+pub mod conformance;
+/// Linters that check a sequence of frames for conflicting information
+pub mod crossframe;
 /// Organize parsers & linters into a single unit
 pub mod dispatcher;
+/// Selection of frames by source, maker code, lints and errors
+pub mod filter;
+/// Repair of trivially fixable lints
+pub mod fixer;
+/// JUnit XML output of lint results, for CI systems
+pub mod junit;
 /// A collection of linters that check the contents of parsed information
 /// for irregularities
 mod linters;
+/// Localised diagnostic messages (English & Japanese)
+pub mod locale;
 /// A collection of parsers that check the contents of tlvs for structural
 /// integrity and extract pieces of parsed information
 mod parsers;
+/// A reader for pcapng capture files
+pub mod pcapng;
+/// Lint policies: disabled lints, severity overrides & suppressions
+pub mod policy;
+/// Tlv layouts defined in schema files, loaded at runtime
+pub mod schema;
+/// Proptest strategies of HTIP frames & tlvs (requires the `strategies` feature)
+#[cfg(feature = "strategies")]
+pub mod strategies;
+/// Asynchronous capture & parsing of HTIP frames (requires the `async` feature)
+#[cfg(feature = "async")]
+pub mod stream;
 mod subkeys;
 /// Type-Length-Value types
 pub mod tlv;
+/// Tlvs laid out as Rust types, e.g. with `#[derive(HtipTlv)]`
+pub mod typed;
+/// Malformed test vectors, generated by mutating a valid frame
+pub mod vectors;
+/// Writers for pcap & pcapng capture files
+pub mod writer;
 
 pub use dispatcher::ParserKey as TlvKey;
-pub use dispatcher::{Dispatcher, InvalidFrame};
-pub use linters::Lint;
+pub use dispatcher::{Dispatcher, InvalidFrame, Mode};
+pub use linters::{Lint, LintInfo, Severity, CATALOGUE};
 pub use parsers::ParseData;
 pub use tlv::{TlvType, TLV};
 
+use locale::{Language, Localize, Localized};
+use macaddr::MacAddr6;
 use std::fmt;
+use std::time::Duration;
 
 #[derive(Debug, PartialEq, Eq)]
+#[non_exhaustive]
 ///These are the errors that a basic parser may produce.
 ///The slice represents the original data that caused
 ///the error.
@@ -53,6 +92,12 @@ pub enum ParsingError<'a> {
     Unknown,
     ///Invalid Frame
     InvalidFrame(&'a [u8]),
+    ///A lint of the frame, in strict mode
+    Lint(Lint),
+    ///No parser for the tag byte of a choice
+    UnknownTag(u8),
+    ///A named part of a combined parser failed, e.g. a field of a record
+    Part(String, Box<ParsingError<'a>>),
 }
 
 /// A lint entry associated with a frame
@@ -60,6 +105,9 @@ pub enum ParsingError<'a> {
 pub struct LintEntry {
     /// [Lint] type
     pub lint: Lint,
+    /// How serious the lint is; the severity of the lint type, unless
+    /// overridden by a [policy::LintPolicy]
+    pub severity: Severity,
     /// Related tlv & prefix
     pub tlv_key: Option<TlvKey>,
     /// Any additional info, used to customize error message
@@ -70,6 +118,7 @@ impl LintEntry {
     /// Create a new LintEntry of the given type
     pub fn new(lint: Lint) -> LintEntry {
         LintEntry {
+            severity: lint.severity(),
             lint,
             tlv_key: None,
             extra_info: None,
@@ -91,15 +140,24 @@ impl LintEntry {
     }
 }
 
-impl fmt::Display for LintEntry {
+impl Localize for LintEntry {}
+
+impl fmt::Display for Localized<'_, LintEntry> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (entry, language) = (self.value, self.language);
+        let severity = entry.severity.localized(language);
+        let severity = match language {
+            Language::English => format!("as {}", severity),
+            Language::Japanese => format!("{}として", severity),
+        };
         write!(
             f,
             "{}",
             vec![
-                Some(self.lint.to_string()),
-                self.tlv_key.clone().map(|tlvkey| tlvkey.to_string()),
-                self.extra_info.clone()
+                Some(entry.lint.localized(language).to_string()),
+                Some(severity).filter(|_| entry.severity != entry.lint.severity()),
+                entry.tlv_key.clone().map(|tlvkey| tlvkey.to_string()),
+                entry.extra_info.clone()
             ]
             .into_iter()
             .filter(|info| info.is_some())
@@ -110,10 +168,161 @@ impl fmt::Display for LintEntry {
     }
 }
 
+impl fmt::Display for LintEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.localized(Language::English).fmt(f)
+    }
+}
+
+impl Localize for ParsingError<'_> {}
+
+impl fmt::Display for Localized<'_, ParsingError<'_>> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.value, self.language) {
+            (ParsingError::TooShort, Language::English) => write!(f, "Not enough data"),
+            (ParsingError::TooShort, Language::Japanese) => write!(f, "データが不足しています"),
+            (ParsingError::UnexpectedLength(len), Language::English) => {
+                write!(f, "Unexpected length {}", len)
+            }
+            (ParsingError::UnexpectedLength(len), Language::Japanese) => {
+                write!(f, "想定外の長さ {}", len)
+            }
+            (ParsingError::NotEqual(bytes), Language::English) => {
+                write!(f, "Unexpected bytes {:02x?}", bytes)
+            }
+            (ParsingError::NotEqual(bytes), Language::Japanese) => {
+                write!(f, "想定外のバイト列 {:02x?}", bytes)
+            }
+            (ParsingError::InvalidPercentage(value), Language::English) => {
+                write!(f, "Invalid percentage {}", value)
+            }
+            (ParsingError::InvalidPercentage(value), Language::Japanese) => {
+                write!(f, "不正なパーセント値 {}", value)
+            }
+            (ParsingError::InvalidText(err), Language::English) => {
+                write!(f, "Invalid UTF-8 text: {}", err)
+            }
+            (ParsingError::InvalidText(err), Language::Japanese) => {
+                write!(f, "不正なUTF-8テキスト: {}", err)
+            }
+            (ParsingError::Unknown, Language::English) => write!(f, "Unknown type/subtype"),
+            (ParsingError::Unknown, Language::Japanese) => {
+                write!(f, "不明なタイプ/サブタイプ")
+            }
+            (ParsingError::InvalidFrame(rest), Language::English) => {
+                write!(f, "Invalid frame, {} bytes left unparsed", rest.len())
+            }
+            (ParsingError::InvalidFrame(rest), Language::Japanese) => {
+                write!(f, "不正なフレーム（未解析 {} バイト）", rest.len())
+            }
+            (ParsingError::Lint(lint), Language::English) => {
+                write!(f, "Strict mode, {}", lint.localized(Language::English))
+            }
+            (ParsingError::Lint(lint), Language::Japanese) => {
+                write!(f, "厳格モード、{}", lint.localized(Language::Japanese))
+            }
+            (ParsingError::UnknownTag(tag), Language::English) => write!(f, "Unknown tag {}", tag),
+            (ParsingError::UnknownTag(tag), Language::Japanese) => write!(f, "不明なタグ {}", tag),
+            (ParsingError::Part(part, error), language) => {
+                write!(f, "{}: {}", part, error.localized(language))
+            }
+        }
+    }
+}
+
+impl fmt::Display for ParsingError<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.localized(Language::English).fmt(f)
+    }
+}
+
 /// Represent the parsing data result for the tlv indicated by key
 pub type InfoEntry = (TlvKey, ParseData);
-/// Represent the parsing error for the tlv indicated by key
-pub type ErrorEntry<'a> = (TlvKey, ParsingError<'a>);
+/// Represent the parsing error for the tlv indicated by key, if the error
+/// is not about the whole frame
+pub type ErrorEntry<'a> = (Option<TlvKey>, ParsingError<'a>);
+
+/// Capture metadata of the packet that carried a frame
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PacketInfo {
+    /// Frame number of the packet in its capture, starting from 0. Every
+    /// packet of a capture file is counted, while a live capture only
+    /// holds the packets its filter lets through.
+    pub index: usize,
+    /// Capture timestamp, since the unix epoch
+    pub timestamp: Duration,
+    /// Number of bytes actually captured
+    pub caplen: u32,
+    /// Original length of the packet on the wire
+    pub len: u32,
+    /// Name of the interface the packet was captured on, if known
+    pub interface: Option<String>,
+    /// Source address of the ethernet frame, if known
+    pub source: Option<MacAddr6>,
+    /// Comments attached to the packet (pcapng)
+    pub comments: Vec<String>,
+}
+
+impl PacketInfo {
+    pub fn new(index: usize, timestamp: Duration, caplen: u32, len: u32) -> PacketInfo {
+        PacketInfo {
+            index,
+            timestamp,
+            caplen,
+            len,
+            interface: None,
+            source: None,
+            comments: vec![],
+        }
+    }
+
+    pub fn with_interface(self, interface: String) -> PacketInfo {
+        PacketInfo {
+            interface: Some(interface),
+            ..self
+        }
+    }
+
+    pub fn with_source(self, source: MacAddr6) -> PacketInfo {
+        PacketInfo {
+            source: Some(source),
+            ..self
+        }
+    }
+
+    pub fn with_comments(self, comments: Vec<String>) -> PacketInfo {
+        PacketInfo { comments, ..self }
+    }
+
+    /// The packet was cut short by the capture (snaplen), not by its sender
+    pub fn is_truncated(&self) -> bool {
+        self.caplen < self.len
+    }
+}
+
+impl fmt::Display for PacketInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "#{} at {}.{:06}, captured {} of {} bytes",
+            self.index,
+            self.timestamp.as_secs(),
+            self.timestamp.subsec_micros(),
+            self.caplen,
+            self.len
+        )?;
+        if let Some(source) = &self.source {
+            write!(f, " from {}", source)?;
+        }
+        if let Some(interface) = &self.interface {
+            write!(f, " on {}", interface)?;
+        }
+        for comment in &self.comments {
+            write!(f, "\n  comment: {}", comment)?;
+        }
+        Ok(())
+    }
+}
 
 /// A structure holding all the relevant information for a
 /// parsed HTIP frame.
@@ -126,31 +335,194 @@ pub struct FrameInfo<'a> {
     pub errors: Vec<ErrorEntry<'a>>,
     /// Additional check results performed by the linters
     pub lints: Vec<LintEntry>,
+    /// Capture metadata, if the frame came from a capture
+    pub packet: Option<PacketInfo>,
 }
 
-impl fmt::Display for FrameInfo<'_> {
+impl FrameInfo<'_> {
+    /// Attach the capture metadata of the packet that carried this frame.
+    /// Issues a lint if the capture truncated the frame, so attach it before
+    /// [Dispatcher::finish()] applies the policy & the mode.
+    pub fn with_packet(mut self, packet: PacketInfo) -> Self {
+        if packet.is_truncated() {
+            self.lints.push(
+                LintEntry::new(Lint::Warning(4))
+                    .with_extra_info(format!("{} of {} bytes", packet.caplen, packet.len)),
+            );
+        }
+        self.packet = Some(packet);
+        self
+    }
+
+    /// A one line summary for each lint & parsing error of this frame,
+    /// e.g. to annotate the frame in a capture file
+    pub fn annotations(&self) -> Vec<String> {
+        self.annotations_in(Language::English)
+    }
+
+    /// [FrameInfo::annotations()], in `language`
+    pub fn annotations_in(&self, language: Language) -> Vec<String> {
+        self.lints
+            .iter()
+            .map(|lint| lint.localized(language).to_string())
+            .chain(self.errors.iter().map(|(key, error)| match key {
+                Some(key) => format!("{}, {}", error.localized(language), key),
+                None => error.localized(language).to_string(),
+            }))
+            .collect()
+    }
+}
+
+impl Localize for FrameInfo<'_> {}
+
+impl fmt::Display for Localized<'_, FrameInfo<'_>> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (info, language) = (self.value, self.language);
+        let (packet, tlvs, infos, errors, lints) = match language {
+            Language::English => ("Packet", "TLVs", "Info", "Errors", "lints"),
+            Language::Japanese => ("パケット", "TLV", "情報", "エラー", "リント"),
+        };
         //concatenate all the tlvs into a string
-        let tlv_string = self
+        let tlv_string = info
             .tlvs
             .iter()
             .map(|tlv| tlv.to_string())
             .collect::<Vec<String>>()
             .join("\n");
+        if let Some(value) = &info.packet {
+            writeln!(f, "{}: {}", packet, value)?;
+        }
         write!(
             f,
-            "TLVs: {}\n\nInfo: {:?}\nErrors: {:?}\n\n",
-            tlv_string, self.info, self.errors,
+            "{}: {}\n\n{}: {:?}\n{}: ",
+            tlvs, tlv_string, infos, info.info, errors
         )?;
-        if !&self.lints.is_empty() {
-            write!(f, "lints: ")?;
-            for i in &self.lints {
-                write!(f, "{}, ", i)?;
+        for (key, error) in &info.errors {
+            match key {
+                Some(key) => write!(f, "{} ({}), ", error.localized(language), key)?,
+                None => write!(f, "{}, ", error.localized(language))?,
+            }
+        }
+        write!(f, "\n\n")?;
+        if !&info.lints.is_empty() {
+            write!(f, "{}: ", lints)?;
+            for i in &info.lints {
+                write!(f, "{}, ", i.localized(language))?;
             }
         }
         Ok(())
     }
 }
 
+impl fmt::Display for FrameInfo<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.localized(Language::English).fmt(f)
+    }
+}
+
 #[cfg(test)]
-mod tests {}
+mod tests {
+    use super::*;
+
+    #[test]
+    fn truncated_packet_issues_lint() {
+        let mut dsp = Dispatcher::new();
+        let packet = PacketInfo::new(0, Duration::from_secs(1), 8, 100);
+        let info = match dsp.parse(b"\x02\x05ABCDE\x00") {
+            Err(bad_frame) => bad_frame.parse(&mut dsp).with_packet(packet.clone()),
+            Ok(_) => panic!("a truncated frame should not parse!"),
+        };
+        assert_eq!(info.packet, Some(packet));
+        assert!(info
+            .lints
+            .iter()
+            .any(|entry| entry.lint == Lint::Warning(4)));
+    }
+
+    #[test]
+    fn complete_packet_does_not_issue_lint() {
+        let mut dsp = Dispatcher::new();
+        let packet = PacketInfo::new(3, Duration::from_millis(1500), 9, 9);
+        let info = match dsp.parse(b"\x02\x05ABCDE\x00\x00") {
+            Ok(info) => info.with_packet(packet),
+            Err(_) => panic!("this should parse, check frame!"),
+        };
+        assert!(info
+            .lints
+            .iter()
+            .all(|entry| entry.lint != Lint::Warning(4)));
+        assert_eq!(
+            info.packet.unwrap().to_string(),
+            "#3 at 1.500000, captured 9 of 9 bytes"
+        );
+    }
+
+    #[test]
+    fn annotations_list_lints_and_errors() {
+        let mut dsp = Dispatcher::new();
+        let info = match dsp.parse(b"\x02\x05ABCDE\x00") {
+            Err(bad_frame) => bad_frame.parse(&mut dsp),
+            Ok(_) => panic!("a truncated frame should not parse!"),
+        };
+        let annotations = info.annotations();
+        assert_eq!(annotations.len(), info.lints.len() + info.errors.len());
+        assert_eq!(annotations[0], "E1: No End TLV");
+        assert!(annotations.last().unwrap().starts_with("Invalid frame"));
+
+        let annotations = info.annotations_in(Language::Japanese);
+        assert_eq!(annotations[0], "E1: End TLVがありません");
+        assert!(annotations.last().unwrap().starts_with("不正なフレーム"));
+    }
+
+    #[test]
+    fn frames_display_in_their_language() {
+        let mut dsp = Dispatcher::new();
+        let info = match dsp.parse(b"\x02\x05ABCDE\x00") {
+            Err(bad_frame) => bad_frame.parse(&mut dsp),
+            Ok(_) => panic!("a truncated frame should not parse!"),
+        };
+        let text = info.localized(Language::Japanese).to_string();
+        assert!(text.starts_with("TLV: "), "{}", text);
+        assert!(text.contains("エラー: 不正なフレーム"), "{}", text);
+        assert!(text.contains("リント: E1: End TLVがありません"), "{}", text);
+        assert!(info.to_string().contains("Errors: Invalid frame"));
+    }
+
+    #[test]
+    fn lint_entries_are_localized() {
+        let entry = LintEntry::new(Lint::Warning(2)).with_extra_info("3 extra bytes".to_string());
+        let entry = LintEntry {
+            severity: Severity::Error,
+            ..entry
+        };
+        assert_eq!(
+            entry.to_string(),
+            "W2: Trailing characters in TLV, as error, 3 extra bytes"
+        );
+        assert_eq!(
+            entry.localized(Language::Japanese).to_string(),
+            "W2: TLVの末尾に余分なデータがあります, エラーとして, 3 extra bytes"
+        );
+    }
+
+    #[test]
+    fn parsing_errors_are_localized() {
+        let error = ParsingError::UnexpectedLength(7);
+        assert_eq!(error.to_string(), "Unexpected length 7");
+        assert_eq!(
+            error.localized(Language::Japanese).to_string(),
+            "想定外の長さ 7"
+        );
+    }
+
+    #[test]
+    fn packet_info_displays_interface_and_comments() {
+        let packet = PacketInfo::new(0, Duration::from_secs(2), 60, 60)
+            .with_interface("eth1".to_string())
+            .with_comments(vec!["first".to_string(), "second".to_string()]);
+        assert_eq!(
+            packet.to_string(),
+            "#0 at 2.000000, captured 60 of 60 bytes on eth1\n  comment: first\n  comment: second"
+        );
+    }
+}
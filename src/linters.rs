@@ -1,32 +1,469 @@
 use lazy_static::lazy_static;
 
-use crate::{InfoEntry, LintEntry, ParseData, TlvKey};
+use crate::dispatcher::TTC_OUI;
+use crate::locale::{Language, Localize, Localized};
+use crate::{LintEntry, ParseData, ParsingError, TlvKey, TlvType, TLV};
 use std::collections::HashMap;
 use std::fmt;
+use std::ops::Range;
+use std::str::FromStr;
+
+/// Catalogue entry of a lint
+#[derive(Debug)]
+pub struct LintInfo {
+    pub lint: Lint,
+    /// Stable identifier, which doesn't change with the numbering of lints
+    pub id: &'static str,
+    /// Short message, in every language it is translated to
+    pub messages: &'static [(Language, &'static str)],
+    /// What the lint means & how to fix it, in every language it is
+    /// translated to
+    pub explanations: &'static [(Language, &'static str)],
+    /// The rule of the specification that is violated, if any
+    pub reference: Option<&'static str>,
+}
+
+impl LintInfo {
+    /// The message in `language`, else in English
+    pub fn message(&self, language: Language) -> &'static str {
+        translation(self.messages, language)
+    }
+
+    /// The explanation in `language`, else in English
+    pub fn explanation(&self, language: Language) -> &'static str {
+        translation(self.explanations, language)
+    }
+}
+
+/// The text of `language` among `translations`, else the English one
+fn translation(translations: &[(Language, &'static str)], language: Language) -> &'static str {
+    [language, Language::English]
+        .iter()
+        .find_map(|language| {
+            translations
+                .iter()
+                .find(|(other, _)| other == language)
+                .map(|(_, text)| *text)
+        })
+        .unwrap_or_default()
+}
+
+impl Localize for LintInfo {}
+
+impl fmt::Display for Localized<'_, LintInfo> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (info, language) = (self.value, self.language);
+        let (severity, see) = match language {
+            Language::English => ("severity", "see"),
+            Language::Japanese => ("重大度", "参照"),
+        };
+        writeln!(
+            f,
+            "{} ({}): {}",
+            info.lint.code(),
+            info.id,
+            info.message(language)
+        )?;
+        writeln!(
+            f,
+            "{}: {}",
+            severity,
+            info.lint.severity().localized(language)
+        )?;
+        write!(f, "{}", info.explanation(language))?;
+        if let Some(reference) = info.reference {
+            write!(f, "\n{}: {}", see, reference)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for LintInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.localized(Language::English).fmt(f)
+    }
+}
+
+const IEEE_LLDPDU: &str = "IEEE 802.1AB-2016, 8.2 (LLDPDU format)";
+const JJ300_MACHINE_INFO: &str = "JJ-300.00 v3, HTIP machine information TLV (subtype 1)";
+
+/// Every lint known to the crate
+pub static CATALOGUE: &[LintInfo] = &[
+    LintInfo {
+        lint: Lint::Error(1),
+        id: "missing-end-tlv",
+        messages: &[
+            (Language::English, "No End TLV"),
+            (Language::Japanese, "End TLVがありません"),
+        ],
+        explanations: &[
+            (Language::English, "Every LLDPDU must be terminated by an End Of LLDPDU TLV \
+                (type 0, length 0)."),
+            (Language::Japanese, "すべてのLLDPDUはEnd Of LLDPDU TLV（タイプ0、長さ0）で終わる必要があります。"),
+        ],
+        reference: Some("IEEE 802.1AB-2016, 8.5.1 (End Of LLDPDU TLV)"),
+    },
+    LintInfo {
+        lint: Lint::Error(2),
+        id: "duplicate-chassis-id",
+        messages: &[
+            (Language::English, "Multiple Type 1 TLVs"),
+            (Language::Japanese, "タイプ1 TLVが複数あります"),
+        ],
+        explanations: &[
+            (Language::English, "An LLDPDU must contain exactly one Chassis ID TLV."),
+            (Language::Japanese, "LLDPDUにはChassis ID TLVがちょうど1つ必要です。"),
+        ],
+        reference: Some(IEEE_LLDPDU),
+    },
+    LintInfo {
+        lint: Lint::Error(3),
+        id: "invalid-chassis-mac",
+        messages: &[
+            (Language::English, "Invalid MAC in Type 1 TLV"),
+            (Language::Japanese, "タイプ1 TLVのMACアドレスが不正です"),
+        ],
+        explanations: &[
+            (Language::English, "A Chassis ID of subtype 4 (MAC address) must hold a 6 or 8 \
+                byte address."),
+            (Language::Japanese, "サブタイプ4（MACアドレス）のChassis IDは6バイトまたは8バイトのアドレスを持つ必要があります。"),
+        ],
+        reference: Some("JJ-300.00 v3, p.27 (Chassis ID TLV)"),
+    },
+    LintInfo {
+        lint: Lint::Error(4),
+        id: "chassis-id-subtype",
+        messages: &[
+            (Language::English, "Type 1 TLV is neither MAC nor locally assigned"),
+            (Language::Japanese, "タイプ1 TLVがMACアドレスでもローカル割当でもありません"),
+        ],
+        explanations: &[
+            (Language::English, "HTIP only allows Chassis IDs of subtype 4 (MAC address) \
+                or 7 (locally assigned)."),
+            (Language::Japanese, "HTIPではサブタイプ4（MACアドレス）または7（ローカル割当）のChassis IDのみ使用できます。"),
+        ],
+        reference: Some("JJ-300.00 v3, p.27 (Chassis ID TLV)"),
+    },
+    LintInfo {
+        lint: Lint::Error(5),
+        id: "mandatory-tlv-order",
+        messages: &[
+            (Language::English, "Chassis ID, Port ID and TTL are not the first three TLVs"),
+            (Language::Japanese, "先頭の3つのTLVがChassis ID、Port ID、TTLではありません"),
+        ],
+        explanations: &[
+            (Language::English, "The Chassis ID, Port ID and Time To Live TLVs must be the \
+                first three TLVs of an LLDPDU, in that order."),
+            (Language::Japanese, "Chassis ID、Port ID、Time To Live TLVは、この順でLLDPDUの先頭の3つのTLVである必要があります。"),
+        ],
+        reference: Some(IEEE_LLDPDU),
+    },
+    LintInfo {
+        lint: Lint::Error(6),
+        id: "missing-mandatory-tlv",
+        messages: &[
+            (Language::English, "Missing mandatory TLV"),
+            (Language::Japanese, "必須TLVがありません"),
+        ],
+        explanations: &[
+            (Language::English, "Every LLDPDU must contain a Chassis ID, a Port ID and a \
+                Time To Live TLV."),
+            (Language::Japanese, "すべてのLLDPDUにはChassis ID、Port ID、Time To Live TLVが必要です。"),
+        ],
+        reference: Some(IEEE_LLDPDU),
+    },
+    LintInfo {
+        lint: Lint::Error(7),
+        id: "duplicate-port-id",
+        messages: &[
+            (Language::English, "Multiple Type 2 TLVs"),
+            (Language::Japanese, "タイプ2 TLVが複数あります"),
+        ],
+        explanations: &[
+            (Language::English, "An LLDPDU must contain exactly one Port ID TLV."),
+            (Language::Japanese, "LLDPDUにはPort ID TLVがちょうど1つ必要です。"),
+        ],
+        reference: Some(IEEE_LLDPDU),
+    },
+    LintInfo {
+        lint: Lint::Error(8),
+        id: "duplicate-ttl",
+        messages: &[
+            (Language::English, "Multiple Type 3 TLVs"),
+            (Language::Japanese, "タイプ3 TLVが複数あります"),
+        ],
+        explanations: &[
+            (Language::English, "An LLDPDU must contain exactly one Time To Live TLV."),
+            (Language::Japanese, "LLDPDUにはTime To Live TLVがちょうど1つ必要です。"),
+        ],
+        reference: Some(IEEE_LLDPDU),
+    },
+    LintInfo {
+        lint: Lint::Error(9),
+        id: "data-after-end",
+        messages: &[
+            (Language::English, "Data after End TLV"),
+            (Language::Japanese, "End TLVの後にデータがあります"),
+        ],
+        explanations: &[
+            (Language::English, "The End Of LLDPDU TLV must be the last TLV; anything after \
+                it is ignored by receivers."),
+            (Language::Japanese, "End Of LLDPDU TLVは最後のTLVである必要があります。その後のデータは受信側で無視されます。"),
+        ],
+        reference: Some("IEEE 802.1AB-2016, 8.5.1 (End Of LLDPDU TLV)"),
+    },
+    LintInfo {
+        lint: Lint::Error(10),
+        id: "missing-machine-info",
+        messages: &[
+            (Language::English, "Missing mandatory HTIP machine information"),
+            (Language::Japanese, "必須のHTIP機器情報がありません"),
+        ],
+        explanations: &[
+            (Language::English, "Every HTIP frame must advertise the device category (ID 1), \
+                maker code (ID 2), model name (ID 3) and model number (ID 4)."),
+            (Language::Japanese, "すべてのHTIPフレームは区分（ID 1）、メーカーコード（ID 2）、機種名（ID 3）、型番（ID 4）を通知する必要があります。"),
+        ],
+        reference: Some(JJ300_MACHINE_INFO),
+    },
+    LintInfo {
+        lint: Lint::Error(11),
+        id: "duplicate-machine-info",
+        messages: &[
+            (Language::English, "Multiple entries of mandatory HTIP machine information"),
+            (Language::Japanese, "必須のHTIP機器情報が重複しています"),
+        ],
+        explanations: &[
+            (Language::English, "The device category, maker code, model name and model \
+                number must be advertised once."),
+            (Language::Japanese, "区分、メーカーコード、機種名、型番はそれぞれ1回だけ通知する必要があります。"),
+        ],
+        reference: Some(JJ300_MACHINE_INFO),
+    },
+    //error(12), machine information out of range, is retired: its ranges
+    //were not sourced from the spec
+    LintInfo {
+        lint: Lint::Error(13),
+        id: "conflicting-device-info",
+        messages: &[
+            (Language::English, "Chassis ID advertises conflicting device information"),
+            (Language::Japanese, "同じChassis IDが異なる機器情報を通知しています"),
+        ],
+        explanations: &[
+            (Language::English, "A device advertised a device category, maker code, model \
+                name or model number different from its earlier frames. This is \
+                either a firmware bug, or another device spoofing its chassis ID."),
+            (Language::Japanese, "機器が以前のフレームと異なる区分、メーカーコード、機種名または\
+                型番を通知しました。ファームウェアの不具合か、他の機器によるChassis IDの\
+                なりすましです。"),
+        ],
+        reference: Some(JJ300_MACHINE_INFO),
+    },
+    LintInfo {
+        lint: Lint::Error(14),
+        id: "duplicate-mac-claim",
+        messages: &[
+            (Language::English, "MAC address claimed by multiple chassis IDs"),
+            (Language::Japanese, "複数のChassis IDが同じMACアドレスを通知しています"),
+        ],
+        explanations: &[
+            (Language::English, "Two devices list the same MAC address in their MAC address \
+                list, which makes the topology ambiguous."),
+            (Language::Japanese, "2つの機器がMACアドレスリストに同じMACアドレスを含めているため、\
+                トポロジーが一意に定まりません。"),
+        ],
+        reference: Some("JJ-300.00 v3, HTIP MAC address list TLV (subtype 3)"),
+    },
+    LintInfo {
+        lint: Lint::Warning(1),
+        id: "invalid-characters",
+        messages: &[
+            (Language::English, "Invalid Characters"),
+            (Language::Japanese, "不正な文字"),
+        ],
+        explanations: &[
+            (Language::English, "The text contains characters outside the character set \
+                allowed for its field."),
+            (Language::Japanese, "テキストに項目で許可されていない文字が含まれています。"),
+        ],
+        reference: Some(JJ300_MACHINE_INFO),
+    },
+    LintInfo {
+        lint: Lint::Warning(2),
+        id: "trailing-data",
+        messages: &[
+            (Language::English, "Trailing characters in TLV"),
+            (Language::Japanese, "TLVの末尾に余分なデータがあります"),
+        ],
+        explanations: &[
+            (Language::English, "The TLV is longer than its contents; the extra bytes are \
+                ignored."),
+            (Language::Japanese, "TLVが内容より長く、余分なバイトは無視されます。"),
+        ],
+        reference: None,
+    },
+    LintInfo {
+        lint: Lint::Warning(3),
+        id: "unknown-tlv",
+        messages: &[
+            (Language::English, "Unknown TLV"),
+            (Language::Japanese, "不明なTLV"),
+        ],
+        explanations: &[
+            (Language::English, "There is no parser for this TLV, so its contents are kept \
+                as binary data."),
+            (Language::Japanese, "このTLVのパーサがないため、内容はバイナリデータとして保持されます。"),
+        ],
+        reference: None,
+    },
+    LintInfo {
+        lint: Lint::Warning(4),
+        id: "truncated-capture",
+        messages: &[
+            (Language::English, "Frame truncated by capture"),
+            (Language::Japanese, "キャプチャによりフレームが切り詰められています"),
+        ],
+        explanations: &[
+            (Language::English, "The capture kept fewer bytes than were sent (snaplen), so \
+                the frame can't be checked completely. This is not an issue of the \
+                sender."),
+            (Language::Japanese, "キャプチャで保存されたバイト数が送信されたバイト数より少ない（snaplen）ため、フレームを完全には検査できません。送信側の問題ではありません。"),
+        ],
+        reference: None,
+    },
+    LintInfo {
+        lint: Lint::Warning(5),
+        id: "unknown-machine-info-id",
+        messages: &[
+            (Language::English, "Unknown HTIP machine information ID"),
+            (Language::Japanese, "不明なHTIP機器情報ID"),
+        ],
+        explanations: &[
+            (Language::English, "The machine information ID is not defined by the spec \
+                (1-4, 20-27, 50-54, 80 and 255 for vendor specific information)."),
+            (Language::Japanese, "この機器情報IDは仕様で定義されていません（1〜4、20〜27、50〜54、80、およびベンダー固有情報の255）。"),
+        ],
+        reference: Some(JJ300_MACHINE_INFO),
+    },
+    //warning(6), unregistered maker code, is retired: no registry of
+    //maker codes could be sourced
+    //warning(7), non-standard device category, is retired: the category
+    //list was not sourced from the annex of the spec
+    LintInfo {
+        lint: Lint::Warning(8),
+        id: "flapping-device-info",
+        messages: &[
+            (Language::English, "Device information flapping between frames"),
+            (Language::Japanese, "機器情報がフレーム間で揺れています"),
+        ],
+        explanations: &[
+            (Language::English, "A device switched back to device information it advertised \
+                before, e.g. two devices sharing a chassis ID."),
+            (Language::Japanese, "機器が以前に通知した機器情報に戻りました。例えば2つの機器が\
+                同じChassis IDを使用している場合に発生します。"),
+        ],
+        reference: Some(JJ300_MACHINE_INFO),
+    },
+    LintInfo {
+        lint: Lint::Warning(9),
+        id: "ttl-shorter-than-period",
+        messages: &[
+            (Language::English, "TTL shorter than the observed transmit period"),
+            (Language::Japanese, "TTLが観測された送信間隔より短いです"),
+        ],
+        explanations: &[
+            (Language::English, "The information of the device expires before its next frame \
+                arrives, so receivers periodically forget the device."),
+            (Language::Japanese, "次のフレームが届く前に機器の情報が失効するため、受信側は定期的に\
+                機器を見失います。"),
+        ],
+        reference: Some("IEEE 802.1AB-2016, 8.5.4 (Time To Live TLV)"),
+    },
+    LintInfo {
+        lint: Lint::Warning(10),
+        id: "update-interval-mismatch",
+        messages: &[
+            (Language::English, "Update interval inconsistent with the observed transmit period"),
+            (Language::Japanese, "更新間隔が観測された送信間隔と一致しません"),
+        ],
+        explanations: &[
+            (Language::English, "The time between two frames of the device is more than \
+                twice, or less than half, its advertised update interval."),
+            (Language::Japanese, "機器の2つのフレームの間隔が、通知された更新間隔の2倍を超えるか、\
+                半分未満です。"),
+        ],
+        reference: Some(JJ300_MACHINE_INFO),
+    },
+    LintInfo {
+        lint: Lint::Warning(11),
+        id: "accepted-deviation",
+        messages: &[
+            (Language::English, "Deviation from the spec accepted in lenient mode"),
+            (Language::Japanese, "寛容モードで仕様からの逸脱を許容しました"),
+        ],
+        explanations: &[
+            (Language::English, "A lenient dispatcher accepts common vendor deviations, such as \
+                sized texts longer than their maximum size or texts padded with NUL \
+                characters, instead of failing to parse them. The value should still be \
+                fixed to conform to the spec."),
+            (Language::Japanese, "寛容モードのディスパッチャは、最大長を超えるサイズ付きテキストや\
+                NUL文字で埋められたテキストなど、よくあるベンダーの逸脱を解析エラーにせず\
+                許容します。値は仕様に準拠するよう修正すべきです。"),
+        ],
+        reference: None,
+    },
+];
 
 lazy_static! {
-    static ref LINTS: HashMap<Lint, &'static str> = {
-        vec![
-            (Lint::Error(1), "No End TLV"),
-            (Lint::Error(2), "Multiple Type 1 TLVs"),
-            (Lint::Error(3), "Invalid MAC in Type 1 TLV"),
-            (
-                Lint::Error(4),
-                "Type 1 TLV is neither MAC nor locally assigned",
-            ),
-            (Lint::Warning(1), "Invalid Characters"),
-            (Lint::Warning(2), "Trailing characters in TLV"),
-            (Lint::Warning(3), "Unknown TLV"),
-        ]
-        .into_iter()
-        .collect()
-    };
+    static ref LINTS: HashMap<Lint, &'static LintInfo> =
+        CATALOGUE.iter().map(|info| (info.lint, info)).collect();
 }
 
 static NODESC: &str = "No Description";
+static NODESC_JA: &str = "説明なし";
+
+/// How serious a lint is
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl Localize for Severity {}
+
+impl fmt::Display for Localized<'_, Severity> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match (self.value, self.language) {
+            (Severity::Warning, Language::English) => "warning",
+            (Severity::Error, Language::English) => "error",
+            (Severity::Warning, Language::Japanese) => "警告",
+            (Severity::Error, Language::Japanese) => "エラー",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.localized(Language::English).fmt(f)
+    }
+}
+
+/// Parse a severity, `warning` or `error`
+impl FromStr for Severity {
+    type Err = String;
+
+    fn from_str(severity: &str) -> Result<Self, Self::Err> {
+        match severity {
+            "warning" => Ok(Severity::Warning),
+            "error" => Ok(Severity::Error),
+            _ => Err(format!("invalid severity: {}", severity)),
+        }
+    }
+}
 
 /// Type of a lint
-#[derive(PartialEq, Eq, Hash, Debug)]
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
 pub enum Lint {
     /// A warning; althoug irregular it can still be used
     Warning(u8),
@@ -34,40 +471,160 @@ pub enum Lint {
     Error(u8),
 }
 
+impl Lint {
+    /// The severity implied by the type of the lint; a
+    /// [LintPolicy](crate::policy::LintPolicy) may override it
+    pub fn severity(&self) -> Severity {
+        match self {
+            Lint::Warning(_) => Severity::Warning,
+            Lint::Error(_) => Severity::Error,
+        }
+    }
+
+    /// The short code of the lint, such as `W2`
+    pub fn code(&self) -> String {
+        match self {
+            Lint::Error(e) => format!("E{}", e),
+            Lint::Warning(w) => format!("W{}", w),
+        }
+    }
+
+    /// The catalogue entry of the lint, if any
+    pub fn info(&self) -> Option<&'static LintInfo> {
+        LINTS.get(self).copied()
+    }
+}
+
+impl Localize for Lint {}
+
+impl fmt::Display for Localized<'_, Lint> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match (self.value.info(), self.language) {
+            (Some(info), language) => info.message(language),
+            (None, Language::English) => NODESC,
+            (None, Language::Japanese) => NODESC_JA,
+        };
+        write!(f, "{}: {}", self.value.code(), message)
+    }
+}
+
 impl fmt::Display for Lint {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Lint::Error(e) => write!(f, "E{}: {}", e, LINTS.get(self).unwrap_or(&NODESC)),
-            Lint::Warning(w) => write!(f, "W{}: {}", w, LINTS.get(self).unwrap_or(&NODESC)),
+        self.localized(Language::English).fmt(f)
+    }
+}
+
+/// Parse a lint code, such as `W2` or `E1`, or a stable identifier, such as
+/// `trailing-data`
+impl FromStr for Lint {
+    type Err = String;
+
+    fn from_str(code: &str) -> Result<Self, Self::Err> {
+        if let Some(info) = CATALOGUE.iter().find(|info| info.id == code) {
+            return Ok(info.lint);
+        }
+        let number = code.get(1..).and_then(|number| number.parse().ok());
+        match (code.chars().next(), number) {
+            (Some('W'), Some(number)) => Ok(Lint::Warning(number)),
+            (Some('E'), Some(number)) => Ok(Lint::Error(number)),
+            _ => Err(format!("invalid lint code: {}", code)),
         }
     }
 }
 
+/// The key & parse result of a single tlv
+pub(crate) type TlvResult<'a> = (TlvKey, Result<ParseData, ParsingError<'a>>);
+
+/// Everything the linters know about a frame: all of its tlvs, including
+/// the ones that failed to parse, along with their parse results
+pub(crate) struct LintContext<'c, 'a> {
+    tlvs: &'c [TLV<'a>],
+    results: &'c [TlvResult<'a>],
+}
+
+impl<'c, 'a> LintContext<'c, 'a> {
+    /// `results` must hold the parse result of each tlv, in the same order
+    pub fn new(tlvs: &'c [TLV<'a>], results: &'c [TlvResult<'a>]) -> Self {
+        assert_eq!(tlvs.len(), results.len());
+        LintContext { tlvs, results }
+    }
+
+    /// All the tlvs of the frame, in their original order
+    pub fn tlvs(&self) -> &'c [TLV<'a>] {
+        self.tlvs
+    }
+
+    /// The position of each tlv in the frame (header included), in their
+    /// original order
+    pub fn spans(&self) -> Vec<Range<usize>> {
+        self.tlvs
+            .iter()
+            .scan(0, |start, tlv| {
+                let span = *start..*start + tlv.len() + 2;
+                *start = span.end;
+                Some(span)
+            })
+            .collect()
+    }
+
+    /// The key & parse result of each tlv (including the parsing errors),
+    /// in their original order
+    pub fn results(&self) -> &'c [TlvResult<'a>] {
+        self.results
+    }
+
+    /// The successfully parsed information, in its original order
+    pub fn info(&self) -> impl Iterator<Item = (&'c TlvKey, &'c ParseData)> {
+        self.results()
+            .iter()
+            .filter_map(|(key, result)| Some((key, result.as_ref().ok()?)))
+    }
+}
+
+/// The textual HTIP machine information (subtype 1) `id` among parsed
+/// information
+fn machine_text<'d>(
+    mut info: impl Iterator<Item = (&'d TlvKey, &'d ParseData)>,
+    id: u8,
+) -> Option<&'d str> {
+    let key = TlvKey::htip(vec![1, id]);
+    info.find_map(|(entry_key, data)| match data {
+        ParseData::Text(text) if *entry_key == key => Some(text.as_str()),
+        _ => None,
+    })
+}
+
+/// The maker code (HTIP subtype 1, info ID 2) among parsed information
+pub(crate) fn maker_code<'d>(
+    info: impl Iterator<Item = (&'d TlvKey, &'d ParseData)>,
+) -> Option<&'d str> {
+    machine_text(info, 2)
+}
+
 /// Checks for abnormal content in parsed information
-pub(crate) trait Linter {
-    /// Check the supplied info entries for abnormal content
+pub(crate) trait Linter: Send {
+    /// Check the supplied frame for abnormal content
     /// # Arguments
     ///
-    /// * `info` - A collection of pieces of information, parsed
-    /// from tlvs, which preserve their original order
-    fn lint(&self, info: &[InfoEntry]) -> Vec<LintEntry>;
+    /// * `context` - All the tlvs of a frame & their parse results,
+    ///   which preserve their original order
+    fn lint(&self, context: &LintContext) -> Vec<LintEntry>;
 }
 
-/// Linter that checks if an End TLV is present
+/// Linter that checks if an End TLV is present. Data after the End TLV is
+/// reported by [TlvOrder].
 pub(crate) struct CheckEndTlv;
 
 impl Linter for CheckEndTlv {
-    fn lint(&self, info: &[InfoEntry]) -> Vec<LintEntry> {
+    fn lint(&self, context: &LintContext) -> Vec<LintEntry> {
         let mut res = vec![];
-        match info.last() {
-            //no end tlv
-            None => res.push(LintEntry::new(Lint::Error(1))),
-            Some(entry) => {
-                if entry.0.tlv_type != 0 {
-                    //last tlv is not end tlv
-                    res.push(LintEntry::new(Lint::Error(1)))
-                }
-            }
+        //even an End TLV that failed to parse is there
+        if !context
+            .tlvs()
+            .iter()
+            .any(|tlv| tlv.tlv_type() == TlvType::End)
+        {
+            res.push(LintEntry::new(Lint::Error(1)))
         }
         res
     }
@@ -126,11 +683,17 @@ impl InvalidChars {
         .collect();
         InvalidChars { allowed }
     }
+
+    /// The characters allowed in the text of `key`, if restricted
+    pub fn allowed(&self, key: &TlvKey) -> Option<&str> {
+        self.allowed.get(key).map(String::as_str)
+    }
 }
 
 impl Linter for InvalidChars {
-    fn lint(&self, info: &[InfoEntry]) -> Vec<LintEntry> {
-        info.iter()
+    fn lint(&self, context: &LintContext) -> Vec<LintEntry> {
+        context
+            .info()
             .filter_map(|(entry_key, entry_pdata)| {
                 Some((self.allowed.get(entry_key)?, entry_key, entry_pdata))
             })
@@ -157,16 +720,21 @@ impl Linter for InvalidChars {
 pub(crate) struct TLV1Linter;
 
 impl Linter for TLV1Linter {
-    fn lint(&self, info: &[InfoEntry]) -> Vec<LintEntry> {
+    fn lint(&self, context: &LintContext) -> Vec<LintEntry> {
         let mut le: Vec<LintEntry> = vec![];
 
-        let dup = info.iter().filter(|(key, _data)| key.tlv_type == 1).count();
+        //every chassis id counts, even those that failed to parse
+        let dup = context
+            .tlvs()
+            .iter()
+            .filter(|tlv| tlv.tlv_type() == TlvType::ChassisID)
+            .count();
         if dup > 1 {
             le.push(LintEntry::new(Lint::Error(2)).with_tlv(TlvKey::new(1, vec![])));
         }
 
-        let l: Vec<LintEntry> = info
-            .iter()
+        let l: Vec<LintEntry> = context
+            .info()
             .filter(|(key, _data)| key.tlv_type == 1)
             .filter_map(|(key, data)| match data {
                 ParseData::TypedData(4u8, d) => {
@@ -195,15 +763,220 @@ impl Linter for TLV1Linter {
     }
 }
 
+///This linter checks the mandatory TLVs of IEEE 802.1AB, which
+///JJ-300.00 also requires:
+/// 1. Chassis ID, Port ID and TTL must be present, else issue error(6)
+/// 2. they must be the first three TLVs, in that order, else issue error(5)
+/// 3. Port ID and TTL must appear once, else issue error(7) and error(8)
+///    (multiple Chassis IDs are reported by [TLV1Linter])
+/// 4. nothing may follow the End TLV, else issue error(9); the ethernet
+///    padding of short frames is not parsed as tlvs
+///
+/// It only looks at tlv types, so it also sees tlvs that failed to parse.
+pub(crate) struct TlvOrder;
+
+impl TlvOrder {
+    const MANDATORY: [TlvType; 3] = [TlvType::ChassisID, TlvType::PortID, TlvType::TimeToLive];
+}
+
+impl Linter for TlvOrder {
+    fn lint(&self, context: &LintContext) -> Vec<LintEntry> {
+        let mut le = vec![];
+        let types = context
+            .tlvs()
+            .iter()
+            .map(|tlv| tlv.tlv_type())
+            .collect::<Vec<_>>();
+        let count = |ttype| types.iter().filter(|&&other| other == ttype).count();
+
+        let missing = TlvOrder::MANDATORY
+            .iter()
+            .filter(|&&ttype| count(ttype) == 0)
+            .map(|&ttype| {
+                LintEntry::new(Lint::Error(6)).with_tlv(TlvKey::new(ttype.into(), vec![]))
+            })
+            .collect::<Vec<_>>();
+        //the order only matters if they are all there
+        if missing.is_empty() && types[..3] != TlvOrder::MANDATORY {
+            le.push(LintEntry::new(Lint::Error(5)));
+        }
+        le.extend(missing);
+
+        if count(TlvType::PortID) > 1 {
+            le.push(LintEntry::new(Lint::Error(7)).with_tlv(TlvKey::new(2, vec![])));
+        }
+        if count(TlvType::TimeToLive) > 1 {
+            le.push(LintEntry::new(Lint::Error(8)).with_tlv(TlvKey::new(3, vec![])));
+        }
+
+        if let Some(end) = types.iter().position(|&ttype| ttype == TlvType::End) {
+            let after = types.len() - end - 1;
+            if after > 0 {
+                let start = context.spans()[end + 1].start;
+                le.push(
+                    LintEntry::new(Lint::Error(9))
+                        .with_tlv(TlvKey::new(0, vec![]))
+                        .with_extra_info(format!(
+                            "{} TLVs after End, starting at byte {}",
+                            after, start
+                        )),
+                );
+            }
+        }
+        le
+    }
+}
+
+///This linter checks the machine information (HTIP subtype 1) of
+///JJ-300.00:
+/// 1. device category, maker code, model name and model number
+///    (IDs 1-4) must be present, else issue error(10)
+/// 2. they must appear once, else issue error(11)
+/// 3. all other IDs must be defined by the spec, else issue warning(5)
+///
+/// Frames without any HTIP tlv are plain LLDP, so they are not checked.
+/// It looks at the raw tlvs, so it also sees tlvs that failed to parse.
+pub(crate) struct HtipMachineInfo;
+
+impl HtipMachineInfo {
+    const MANDATORY: [u8; 4] = [1, 2, 3, 4];
+
+    /// Info IDs of subtype 1 defined by the spec
+    fn is_defined(id: u8) -> bool {
+        matches!(id, 1..=4 | 20..=27 | 50..=54 | 80 | 255)
+    }
+
+    fn key(id: u8) -> TlvKey {
+        TlvKey::htip(vec![1, id])
+    }
+}
+
+impl Linter for HtipMachineInfo {
+    fn lint(&self, context: &LintContext) -> Vec<LintEntry> {
+        let htip = context
+            .tlvs()
+            .iter()
+            .filter(|tlv| tlv.tlv_type() == TlvType::Custom)
+            .map(|tlv| tlv.value())
+            .filter(|value| value.starts_with(TTC_OUI))
+            .collect::<Vec<_>>();
+        if htip.is_empty() {
+            return vec![];
+        }
+
+        //OUI, subtype 1, info ID
+        let ids = htip
+            .iter()
+            .filter(|value| value.get(3) == Some(&1))
+            .filter_map(|value| value.get(4).copied())
+            .collect::<Vec<_>>();
+        let count = |id| ids.iter().filter(|&&other| other == id).count();
+
+        let mut le = vec![];
+        for &id in HtipMachineInfo::MANDATORY.iter() {
+            match count(id) {
+                0 => le.push(LintEntry::new(Lint::Error(10)).with_tlv(HtipMachineInfo::key(id))),
+                1 => {}
+                n => le.push(
+                    LintEntry::new(Lint::Error(11))
+                        .with_tlv(HtipMachineInfo::key(id))
+                        .with_extra_info(format!("{} entries", n)),
+                ),
+            }
+        }
+        le.extend(
+            ids.iter()
+                .filter(|&&id| !HtipMachineInfo::is_defined(id))
+                .map(|&id| {
+                    LintEntry::new(Lint::Warning(5))
+                        .with_tlv(HtipMachineInfo::key(id))
+                        .with_extra_info(format!("ID {}", id))
+                }),
+        );
+        le
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::InfoEntry;
+
+    /// Lint successfully parsed entries, with a fake tlv for each one
+    fn lint_info(linter: &dyn Linter, entries: Vec<InfoEntry>) -> Vec<LintEntry> {
+        let tlvs = entries
+            .iter()
+            .map(|(key, _)| TLV::new(TlvType::from(key.tlv_type), 0, b""))
+            .collect::<Vec<_>>();
+        let results = entries
+            .into_iter()
+            .map(|(key, data)| (key, Ok(data)))
+            .collect::<Vec<_>>();
+        linter.lint(&LintContext::new(&tlvs, &results))
+    }
+
+    /// Lint tlvs of the given types, all with empty values that parsed fine
+    fn lint_types(linter: &dyn Linter, types: &[u8]) -> Vec<LintEntry> {
+        let entries = types
+            .iter()
+            .map(|&ttype| (TlvKey::new(ttype, vec![]), ParseData::Null))
+            .collect();
+        lint_info(linter, entries)
+    }
+
+    #[test]
+    fn lint_codes_parse() {
+        assert_eq!("W2".parse(), Ok(Lint::Warning(2)));
+        assert_eq!("E14".parse(), Ok(Lint::Error(14)));
+        assert!("X1".parse::<Lint>().is_err());
+        assert!("W".parse::<Lint>().is_err());
+        assert!("E256".parse::<Lint>().is_err());
+        assert_eq!("trailing-data".parse(), Ok(Lint::Warning(2)));
+    }
+
+    #[test]
+    fn catalogue_entries_are_unique() {
+        for (index, info) in CATALOGUE.iter().enumerate() {
+            assert!(CATALOGUE[index + 1..]
+                .iter()
+                .all(|other| other.lint != info.lint && other.id != info.id));
+            assert_eq!(info.lint.info().map(|other| other.id), Some(info.id));
+        }
+    }
+
+    #[test]
+    fn catalogue_entries_are_translated() {
+        for info in CATALOGUE {
+            for texts in &[info.messages, info.explanations] {
+                assert!(texts
+                    .iter()
+                    .any(|(language, _)| *language == Language::English));
+                assert!(texts
+                    .iter()
+                    .any(|(language, _)| *language == Language::Japanese));
+            }
+        }
+    }
+
+    #[test]
+    fn lints_display_their_message() {
+        assert_eq!(
+            Lint::Warning(2).to_string(),
+            "W2: Trailing characters in TLV"
+        );
+        assert_eq!(Lint::Error(200).to_string(), "E200: No Description");
+        assert!(Lint::Error(1)
+            .info()
+            .unwrap()
+            .to_string()
+            .starts_with("E1 (missing-end-tlv): No End TLV\nseverity: error\n"));
+    }
 
     #[test]
     fn check_end_tlv_lints_on_empty_input() {
         let entries = vec![];
         let linter = CheckEndTlv;
-        let result = linter.lint(&entries);
+        let result = lint_info(&linter, entries);
         assert_eq!(result.len(), 1);
         assert_eq!(result[0].lint, Lint::Error(1));
     }
@@ -212,7 +985,7 @@ mod tests {
     fn check_end_tlv_lints_on_wrong_last_entry() {
         let entries = vec![(TlvKey::new(1, vec![]), ParseData::Null)];
         let linter = CheckEndTlv;
-        let result = linter.lint(&entries);
+        let result = lint_info(&linter, entries);
         assert_eq!(result.len(), 1);
         assert_eq!(result[0].lint, Lint::Error(1));
     }
@@ -221,7 +994,7 @@ mod tests {
     fn check_end_tlv_doesnt_lint_on_correct_last_entry() {
         let entries = vec![(TlvKey::new(0, vec![]), ParseData::Null)];
         let linter = CheckEndTlv;
-        let result = linter.lint(&entries);
+        let result = lint_info(&linter, entries);
         assert_eq!(result.len(), 0);
     }
 
@@ -235,7 +1008,7 @@ mod tests {
             (TlvKey::new(0, b"".to_vec()), ParseData::Null),
         ];
         let linter = InvalidChars::new();
-        let result = linter.lint(&entries);
+        let result = lint_info(&linter, entries);
         assert_eq!(result.len(), 1);
         assert_eq!(result[0].lint, Lint::Warning(1));
         assert_eq!(
@@ -264,7 +1037,7 @@ mod tests {
             (TlvKey::new(0, b"".to_vec()), ParseData::Null),
         ];
         let linter = InvalidChars::new();
-        let result = linter.lint(&entries);
+        let result = lint_info(&linter, entries);
         assert_eq!(result.len(), 1);
         assert_eq!(
             result[0]
@@ -296,7 +1069,7 @@ mod tests {
             ),
         ];
         let linter = InvalidChars::new();
-        let result = linter.lint(&entries);
+        let result = lint_info(&linter, entries);
         assert_eq!(result.len(), 2);
         assert_eq!(
             result[0]
@@ -336,7 +1109,7 @@ mod tests {
             ),
         ];
         let linter = InvalidChars::new();
-        let result = linter.lint(&entries);
+        let result = lint_info(&linter, entries);
         assert_eq!(result.len(), 2);
         assert_eq!(
             result[0]
@@ -369,7 +1142,7 @@ mod tests {
             ),
         ];
         let linter = TLV1Linter;
-        let result = linter.lint(&entries);
+        let result = lint_info(&linter, entries);
 
         let many_tlvs_lint = result
             .into_iter()
@@ -380,6 +1153,23 @@ mod tests {
         }
     }
 
+    #[test]
+    fn tlv1linter_counts_unparsed_chassis_ids() {
+        let tlvs = vec![
+            TLV::new(TlvType::ChassisID, 7, b"\x04ABCDEF"),
+            TLV::new(TlvType::ChassisID, 1, b"\x04"),
+        ];
+        let results = vec![
+            (
+                TlvKey::new(1, vec![]),
+                Ok(ParseData::TypedData(4, b"ABCDEF".to_vec())),
+            ),
+            (TlvKey::new(1, vec![]), Err(ParsingError::TooShort)),
+        ];
+        let result = TLV1Linter.lint(&LintContext::new(&tlvs, &results));
+        assert!(result.iter().any(|entry| entry.lint == Lint::Error(2)));
+    }
+
     #[test]
     fn tlv1linter_invalid_mac() {
         let entries = vec![
@@ -390,7 +1180,7 @@ mod tests {
             ),
         ];
         let linter = TLV1Linter;
-        let result = linter.lint(&entries);
+        let result = lint_info(&linter, entries);
 
         let many_tlvs_lint = result
             .into_iter()
@@ -409,7 +1199,7 @@ mod tests {
             ParseData::TypedData(4, b"ABCDEF".to_vec()),
         )];
         let linter = TLV1Linter;
-        let result = linter.lint(&entries);
+        let result = lint_info(&linter, entries);
         assert!(result.is_empty(), "there should not be any errors here!");
     }
 
@@ -421,7 +1211,7 @@ mod tests {
             ParseData::TypedData(4, b"ABCDEF12".to_vec()),
         )];
         let linter = TLV1Linter;
-        let result = linter.lint(&entries);
+        let result = lint_info(&linter, entries);
         assert!(result.is_empty(), "there should not be any errors here!");
     }
 
@@ -432,13 +1222,10 @@ mod tests {
             ParseData::TypedData(7, b"locally assigned string here".to_vec()),
         )];
         let linter = TLV1Linter;
-        let result = linter.lint(&entries);
+        let result = lint_info(&linter, entries);
         assert_eq!(result.len(), 1);
         assert_eq!(result[0].lint, Lint::Warning(1));
-        assert_eq!(
-            result[0].tlv_key.as_ref().unwrap(),
-            &TlvKey::new(1.into(), vec![])
-        );
+        assert_eq!(result[0].tlv_key.as_ref().unwrap(), &TlvKey::new(1, vec![]));
     }
 
     #[test]
@@ -448,7 +1235,7 @@ mod tests {
             ParseData::TypedData(7, b"ABCDEF".to_vec()),
         )];
         let linter = TLV1Linter;
-        let result = linter.lint(&entries);
+        let result = lint_info(&linter, entries);
         assert!(
             result.is_empty(),
             "raised a lint where there shouldn't be one!"
@@ -462,7 +1249,7 @@ mod tests {
             ParseData::TypedData(4, b"just happened to be type 4".to_vec()),
         )];
         let linter = TLV1Linter;
-        let result = linter.lint(&entries);
+        let result = lint_info(&linter, entries);
         assert!(
             result.is_empty(),
             "raised a lint where there shouldn't be one!"
@@ -475,12 +1262,158 @@ mod tests {
             ParseData::TypedData(6, b"type six data, i don't even know what this is".to_vec()),
         )];
         let linter = TLV1Linter;
-        let result = linter.lint(&entries);
+        let result = lint_info(&linter, entries);
         assert_eq!(result.len(), 1);
         assert_eq!(result[0].lint, Lint::Error(4));
+        assert_eq!(result[0].tlv_key.as_ref().unwrap(), &TlvKey::new(1, vec![]));
+    }
+
+    fn lints(result: &[LintEntry]) -> Vec<&Lint> {
+        result.iter().map(|entry| &entry.lint).collect()
+    }
+
+    #[test]
+    fn tlv_order_no_lint_on_correct_order() {
+        let result = lint_types(&TlvOrder, &[1, 2, 3, 4, 127, 0]);
+        assert!(result.is_empty(), "there should not be any errors here!");
+    }
+
+    #[test]
+    fn tlv_order_lints_on_wrong_order() {
+        let result = lint_types(&TlvOrder, &[2, 1, 3, 0]);
+        assert_eq!(lints(&result), vec![&Lint::Error(5)]);
+
+        let result = lint_types(&TlvOrder, &[1, 2, 4, 3, 0]);
+        assert_eq!(lints(&result), vec![&Lint::Error(5)]);
+    }
+
+    #[test]
+    fn tlv_order_lints_on_missing_tlvs() {
+        let result = lint_types(&TlvOrder, &[1, 127, 0]);
+        assert_eq!(lints(&result), vec![&Lint::Error(6), &Lint::Error(6)]);
+        assert_eq!(result[0].tlv_key, Some(TlvKey::new(2, vec![])));
+        assert_eq!(result[1].tlv_key, Some(TlvKey::new(3, vec![])));
+
+        let result = lint_types(&TlvOrder, &[]);
+        assert_eq!(result.len(), 3);
+    }
+
+    #[test]
+    fn tlv_order_lints_on_duplicates() {
+        let result = lint_types(&TlvOrder, &[1, 2, 3, 2, 3, 3, 0]);
+        assert_eq!(lints(&result), vec![&Lint::Error(7), &Lint::Error(8)]);
+    }
+
+    #[test]
+    fn tlv_order_lints_on_data_after_end() {
+        let result = lint_types(&TlvOrder, &[1, 2, 3, 0, 4, 0]);
+        assert_eq!(lints(&result), vec![&Lint::Error(9)]);
+        assert_eq!(
+            result[0].extra_info.as_deref(),
+            Some("2 TLVs after End, starting at byte 8")
+        );
+    }
+
+    #[test]
+    fn check_end_tlv_doesnt_lint_on_end_tlv_that_failed_to_parse() {
+        let tlvs = vec![
+            TLV::new(TlvType::ChassisID, 0, b""),
+            TLV::new(TlvType::End, 1, b"x"),
+        ];
+        let results = vec![
+            (TlvKey::new(1, vec![]), Ok(ParseData::Null)),
+            (
+                TlvKey::new(0, vec![]),
+                Err(ParsingError::UnexpectedLength(1)),
+            ),
+        ];
+        let context = LintContext::new(&tlvs, &results);
+        assert!(CheckEndTlv.lint(&context).is_empty());
+        assert_eq!(context.info().count(), 1);
+        assert!(context.results()[1].1.is_err());
+    }
+
+    #[test]
+    fn lint_context_spans_include_headers() {
+        let tlvs = vec![
+            TLV::new(TlvType::ChassisID, 3, b"abc"),
+            TLV::new(TlvType::PortID, 0, b""),
+            TLV::new(TlvType::End, 0, b""),
+        ];
+        let results = tlvs
+            .iter()
+            .map(|tlv| {
+                (
+                    TlvKey::new(tlv.tlv_type().into(), vec![]),
+                    Ok(ParseData::Null),
+                )
+            })
+            .collect::<Vec<_>>();
+        let context = LintContext::new(&tlvs, &results);
+        assert_eq!(context.spans(), vec![0..5, 5..7, 7..9]);
+    }
+
+    /// Lint a frame with the mandatory LLDP tlvs & the given HTIP machine
+    /// info IDs; all tlvs failed to parse
+    fn lint_htip(ids: &[u8]) -> Vec<LintEntry> {
+        let values = ids
+            .iter()
+            .map(|&id| vec![0xe0, 0x27, 0x1a, 1, id, 0])
+            .collect::<Vec<_>>();
+        let tlvs = [TlvType::ChassisID, TlvType::PortID, TlvType::TimeToLive]
+            .iter()
+            .map(|&ttype| TLV::new(ttype, 0, b""))
+            .chain(
+                values
+                    .iter()
+                    .map(|value| TLV::new(TlvType::Custom, value.len(), value)),
+            )
+            .chain(std::iter::once(TLV::new(TlvType::End, 0, b"")))
+            .collect::<Vec<_>>();
+        let results = tlvs
+            .iter()
+            .map(|tlv| {
+                let key = TlvKey::new(tlv.tlv_type().into(), vec![]);
+                (key, Err(ParsingError::UnexpectedLength(0)))
+            })
+            .collect::<Vec<_>>();
+        HtipMachineInfo.lint(&LintContext::new(&tlvs, &results))
+    }
+
+    #[test]
+    fn htip_machine_info_accepts_mandatory_info() {
+        assert!(lint_htip(&[1, 2, 3, 4, 20, 50, 80, 255]).is_empty());
+    }
+
+    #[test]
+    fn htip_machine_info_ignores_plain_lldp() {
+        assert!(lint_types(&HtipMachineInfo, &[1, 2, 3, 0]).is_empty());
+    }
+
+    #[test]
+    fn htip_machine_info_lints_on_missing_info() {
+        let result = lint_htip(&[1, 3]);
+        assert_eq!(lints(&result), vec![&Lint::Error(10), &Lint::Error(10)]);
+        assert_eq!(result[0].tlv_key, Some(TlvKey::htip(b"\x01\x02".to_vec())));
+        assert_eq!(result[1].tlv_key, Some(TlvKey::htip(b"\x01\x04".to_vec())));
+    }
+
+    #[test]
+    fn htip_machine_info_lints_on_duplicates() {
+        let result = lint_htip(&[1, 2, 2, 3, 4, 4, 4]);
+        assert_eq!(lints(&result), vec![&Lint::Error(11), &Lint::Error(11)]);
+        assert_eq!(result[1].tlv_key, Some(TlvKey::htip(b"\x01\x04".to_vec())));
+        assert_eq!(result[1].extra_info.as_deref(), Some("3 entries"));
+    }
+
+    #[test]
+    fn htip_machine_info_lints_on_unknown_ids() {
+        let result = lint_htip(&[1, 2, 3, 4, 5, 28, 80, 81]);
         assert_eq!(
-            result[0].tlv_key.as_ref().unwrap(),
-            &TlvKey::new(1.into(), vec![])
+            lints(&result),
+            vec![&Lint::Warning(5), &Lint::Warning(5), &Lint::Warning(5)]
         );
+        assert_eq!(result[1].tlv_key, Some(TlvKey::htip(b"\x01\x1c".to_vec())));
+        assert_eq!(result[2].extra_info.as_deref(), Some("ID 81"));
     }
 }
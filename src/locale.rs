@@ -0,0 +1,85 @@
+use std::env;
+use std::str::FromStr;
+
+/// Language of diagnostic messages
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Language {
+    #[default]
+    English,
+    Japanese,
+}
+
+impl Language {
+    /// The language of a locale or language tag, such as `ja_JP.UTF-8`,
+    /// `ja` or `en-US`
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        let language = tag.split(['_', '-', '.', '@']).next()?;
+        match language.to_ascii_lowercase().as_str() {
+            "ja" => Some(Language::Japanese),
+            "en" | "c" | "posix" => Some(Language::English),
+            _ => None,
+        }
+    }
+
+    /// The language of the environment (`LC_ALL`, `LC_MESSAGES` or `LANG`),
+    /// English if unset or unsupported
+    pub fn from_env() -> Self {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|name| env::var(name).ok())
+            .find(|value| !value.is_empty())
+            .and_then(|value| Language::from_tag(&value))
+            .unwrap_or_default()
+    }
+}
+
+/// Parse a language tag, see [Language::from_tag()]
+impl FromStr for Language {
+    type Err = String;
+
+    fn from_str(tag: &str) -> Result<Self, Self::Err> {
+        Language::from_tag(tag).ok_or(format!("unsupported language: {}", tag))
+    }
+}
+
+/// A value that displays in a given language
+///
+/// # Examples
+///
+/// ```
+/// use rust_htip::locale::{Language, Localize};
+/// use rust_htip::Lint;
+///
+/// let lint = Lint::Error(1);
+/// assert_eq!(lint.localized(Language::English).to_string(), "E1: No End TLV");
+/// assert_eq!(lint.localized(Language::Japanese).to_string(), "E1: End TLVがありません");
+/// ```
+pub struct Localized<'a, T: ?Sized> {
+    pub value: &'a T,
+    pub language: Language,
+}
+
+/// Values with localised messages
+pub trait Localize {
+    fn localized(&self, language: Language) -> Localized<'_, Self> {
+        Localized {
+            value: self,
+            language,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tags_are_recognised() {
+        assert_eq!(Language::from_tag("ja_JP.UTF-8"), Some(Language::Japanese));
+        assert_eq!(Language::from_tag("ja"), Some(Language::Japanese));
+        assert_eq!(Language::from_tag("en-US"), Some(Language::English));
+        assert_eq!(Language::from_tag("C"), Some(Language::English));
+        assert_eq!(Language::from_tag("fr_FR"), None);
+        assert!("de".parse::<Language>().is_err());
+    }
+}
@@ -5,32 +5,62 @@ use macaddr::MacAddr6;
 
 use super::ParsingError;
 
-pub(crate) trait Parser {
+///Parses the data of a [Context], consuming what it parsed
+pub trait Parser: Send {
     fn parse<'a, 's>(
         &mut self,
         context: &'a mut Context<'s>,
     ) -> Result<ParseData, ParsingError<'s>>;
 }
 
-pub(crate) struct Context<'a> {
+///The data left to parse. A parser reads it with [Context::get()] and
+///consumes what it parsed with [Context::set()].
+pub struct Context<'a> {
     data: &'a [u8],
+    /// Accept common deviations from the spec, noting them down
+    lenient: bool,
+    deviations: Vec<String>,
 }
 
 impl<'a> Context<'a> {
     pub fn new(data: &'a [u8]) -> Self {
-        Context { data }
+        Context {
+            data,
+            lenient: false,
+            deviations: vec![],
+        }
+    }
+
+    pub(crate) fn lenient(self, lenient: bool) -> Self {
+        Context { lenient, ..self }
+    }
+
+    pub(crate) fn is_lenient(&self) -> bool {
+        self.lenient
+    }
+
+    /// Note down a deviation from the spec that was accepted
+    pub(crate) fn deviate(&mut self, deviation: String) {
+        self.deviations.push(deviation);
+    }
+
+    /// The deviations accepted so far
+    pub(crate) fn take_deviations(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.deviations)
     }
 
+    ///Continue with `data`, usually the rest of [Context::get()]
     pub fn set(&mut self, data: &'a [u8]) {
         self.data = data;
     }
 
+    ///The data left to parse
     pub fn get(&mut self) -> &'a [u8] {
         self.data
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 ///An enum holding the various possible types of HTIP data.
 pub enum ParseData {
     ///Represents a number of up to 4 bytes, as well as percentages.
@@ -47,6 +77,10 @@ pub enum ParseData {
     Connections(PerPortInfo),
     ///typed data
     TypedData(u8, Vec<u8>),
+    ///Named fields, in order (e.g. of a tlv defined by a schema)
+    Record(Vec<(String, ParseData)>),
+    ///Repeated data
+    List(Vec<ParseData>),
     ///No data (end tlv)
     Null,
 }
@@ -157,7 +191,7 @@ pub enum NumberSize {
 }
 
 ///A parser for numbers that declare their sizes, with a known max size in bytes.
-pub(crate) struct SizedNumber {
+pub struct SizedNumber {
     size: NumberSize,
 }
 
@@ -233,7 +267,8 @@ impl Parser for Dummy {
     }
 }
 
-pub(crate) struct AnyBinary;
+///Takes all the data as is
+pub struct AnyBinary;
 
 impl Parser for AnyBinary {
     fn parse<'a, 's>(&mut self, ctx: &'a mut Context<'s>) -> Result<ParseData, ParsingError<'s>> {
@@ -272,7 +307,8 @@ impl Parser for FixedSequence {
     }
 }
 
-pub(crate) struct Text {
+///A utf8 text of up to `max_size` bytes, taking all the data
+pub struct Text {
     max_size: usize,
 }
 
@@ -302,13 +338,27 @@ impl Parser for Text {
             Err(err) => Err(ParsingError::InvalidText(err.utf8_error())),
             Ok(text) => {
                 ctx.set(&input[text.len()..]);
-                Ok(ParseData::Text(text))
+                Ok(ParseData::Text(trim_nul(ctx, text)))
             }
         }
     }
 }
 
-pub(crate) struct SizedText {
+/// In lenient mode, drop the NUL characters some vendors pad texts with
+fn trim_nul(ctx: &mut Context, text: String) -> String {
+    if !ctx.is_lenient() || !text.ends_with('\0') {
+        return text;
+    }
+    let trimmed = text.trim_end_matches('\0');
+    ctx.deviate(format!(
+        "{} trailing NUL characters",
+        text.len() - trimmed.len()
+    ));
+    trimmed.to_string()
+}
+
+///A utf8 text after its 1 byte size, of up to `max_size` bytes
+pub struct SizedText {
     max_size: usize,
 }
 
@@ -317,6 +367,7 @@ impl SizedText {
         SizedText { max_size }
     }
 
+    ///A text of exactly `size` bytes
     pub fn exact(size: usize) -> ExactlySizedText {
         ExactlySizedText {
             inner: SizedText::new(size),
@@ -347,7 +398,14 @@ impl Parser for SizedText {
         //first byte is the declared length
         //check against maximum expected size & that we have enough input
         let text_size = *input.get(0).ok_or(ParsingError::TooShort)? as usize;
-        SizedText::check_max_size(self.max_size, text_size)?;
+        match SizedText::check_max_size(self.max_size, text_size) {
+            //lenient: the data is there, accept it
+            Err(_) if ctx.is_lenient() && text_size < input.len() => ctx.deviate(format!(
+                "text size {} over maximum {}",
+                text_size, self.max_size
+            )),
+            result => result?,
+        }
         SizedText::check_input_size(text_size, &input[1..])?;
 
         //we have enough data. Try to parse a utf8-string
@@ -358,13 +416,14 @@ impl Parser for SizedText {
             Ok(text) => {
                 //input = &mut input[text_size + 1..];
                 ctx.set(&input[text_size + 1..]);
-                Ok(ParseData::Text(text))
+                Ok(ParseData::Text(trim_nul(ctx, text)))
             }
         }
     }
 }
 
-pub(crate) struct ExactlySizedText {
+///A [SizedText] of an exact size, see [SizedText::exact()]
+pub struct ExactlySizedText {
     inner: SizedText,
     exact_size: usize,
 }
@@ -390,7 +449,14 @@ impl Parser for ExactlySizedText {
     }
 }
 
-pub(crate) struct Percentage;
+///A percentage (0-100) after its 1 byte size
+pub struct Percentage;
+
+impl Default for Percentage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 impl Percentage {
     pub fn new() -> Self {
@@ -436,7 +502,7 @@ impl CompositeParser {
 
     pub fn extractor<F>(self, func: F) -> CompositeParserComplete
     where
-        F: 'static + Fn(&mut Vec<ParseData>) -> ParseData,
+        F: 'static + Send + Fn(&mut Vec<ParseData>) -> ParseData,
     {
         CompositeParserComplete {
             parts: self.parts,
@@ -449,11 +515,13 @@ impl CompositeParser {
 pub(crate) struct CompositeParserComplete {
     parts: Vec<Box<dyn Parser>>,
     data: Vec<ParseData>,
-    func: Box<dyn Fn(&mut Vec<ParseData>) -> ParseData>,
+    func: Box<dyn Fn(&mut Vec<ParseData>) -> ParseData + Send>,
 }
 
 impl Parser for CompositeParserComplete {
     fn parse<'a, 's>(&mut self, input: &'a mut Context<'s>) -> Result<ParseData, ParsingError<'s>> {
+        //drop the parts of a previous failed parse
+        self.data.clear();
         for parser in &mut self.parts {
             self.data.push(parser.parse(input)?);
         }
@@ -461,7 +529,14 @@ impl Parser for CompositeParserComplete {
     }
 }
 
-pub(crate) struct Mac;
+///A list of MAC addresses after their 1 byte count
+pub struct Mac;
+
+impl Default for Mac {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 impl Mac {
     pub fn new() -> Self {
@@ -490,7 +565,7 @@ impl Parser for Mac {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct PerPortInfo {
     pub interface: u32,
     pub port: u32,
@@ -563,6 +638,12 @@ impl Parser for Number {
 
 pub struct TypedData;
 
+impl Default for TypedData {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl TypedData {
     pub fn new() -> Self {
         TypedData {}
@@ -597,6 +678,202 @@ impl Parser for NoData {
     }
 }
 
+/// How many times [Repeat] runs its parser
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Count {
+    Times(usize),
+    /// Until no data is left
+    UntilEnd,
+}
+
+///Runs a parser a number of times, into a [ParseData::List]. Errors name
+///the failed item, counting from 0.
+///
+/// # Examples
+///
+/// ```
+/// use rust_htip::combinators::{Context, LengthPrefixed, NumberSize, Parser, Repeat, Text};
+/// use rust_htip::ParseData;
+///
+/// //every name after its 1 byte length
+/// let name = LengthPrefixed::new(NumberSize::One, Box::new(Text::new(255)));
+/// let mut parser = Repeat::until_end(Box::new(name));
+/// let data = parser.parse(&mut Context::new(b"\x02ab\x01c"));
+/// let names = vec![
+///     ParseData::Text("ab".to_string()),
+///     ParseData::Text("c".to_string()),
+/// ];
+/// assert_eq!(data, Ok(ParseData::List(names)));
+/// ```
+pub struct Repeat {
+    parser: Box<dyn Parser>,
+    count: Count,
+}
+
+impl Repeat {
+    pub fn times(count: usize, parser: Box<dyn Parser>) -> Self {
+        Repeat {
+            parser,
+            count: Count::Times(count),
+        }
+    }
+
+    pub fn until_end(parser: Box<dyn Parser>) -> Self {
+        Repeat {
+            parser,
+            count: Count::UntilEnd,
+        }
+    }
+}
+
+impl Parser for Repeat {
+    fn parse<'s>(&mut self, ctx: &mut Context<'s>) -> Result<ParseData, ParsingError<'s>> {
+        let mut items = vec![];
+        loop {
+            let left = ctx.data.len();
+            match self.count {
+                Count::Times(count) if items.len() == count => break,
+                Count::UntilEnd if left == 0 => break,
+                _ => (),
+            }
+            let item = self.parser.parse(ctx).map_err(|err| {
+                ParsingError::Part(format!("item {}", items.len()), Box::new(err))
+            })?;
+            items.push(item);
+            //a parser that uses no data would repeat forever
+            if self.count == Count::UntilEnd && ctx.data.len() == left {
+                break;
+            }
+        }
+        Ok(ParseData::List(items))
+    }
+}
+
+///Runs a parser if any data is left, otherwise returns [ParseData::Null]
+pub struct Optional {
+    parser: Box<dyn Parser>,
+}
+
+impl Optional {
+    pub fn new(parser: Box<dyn Parser>) -> Self {
+        Optional { parser }
+    }
+}
+
+impl Parser for Optional {
+    fn parse<'s>(&mut self, ctx: &mut Context<'s>) -> Result<ParseData, ParsingError<'s>> {
+        if ctx.data.is_empty() {
+            Ok(ParseData::Null)
+        } else {
+            self.parser.parse(ctx)
+        }
+    }
+}
+
+///Picks the parser of the tag byte in front of the data, into a
+///[ParseData::Record] of the name of the tag & the parsed data
+#[derive(Default)]
+pub struct Choice {
+    options: Vec<(u8, String, Box<dyn Parser>)>,
+}
+
+impl Choice {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_option(mut self, tag: u8, name: &str, parser: Box<dyn Parser>) -> Self {
+        self.options.push((tag, name.to_string(), parser));
+        self
+    }
+}
+
+impl Parser for Choice {
+    fn parse<'s>(&mut self, ctx: &mut Context<'s>) -> Result<ParseData, ParsingError<'s>> {
+        let input = ctx.data;
+        let tag = *input.first().ok_or(ParsingError::TooShort)?;
+        let (_, name, parser) = self
+            .options
+            .iter_mut()
+            .find(|(option, _, _)| *option == tag)
+            .ok_or(ParsingError::UnknownTag(tag))?;
+        ctx.set(&input[1..]);
+        let data = parser
+            .parse(ctx)
+            .map_err(|err| ParsingError::Part(name.clone(), Box::new(err)))?;
+        Ok(ParseData::Record(vec![(name.clone(), data)]))
+    }
+}
+
+///Runs a parser on the data after its length, a number of `size` bytes.
+///The parser must use up all of that data.
+pub struct LengthPrefixed {
+    size: NumberSize,
+    parser: Box<dyn Parser>,
+}
+
+impl LengthPrefixed {
+    pub fn new(size: NumberSize, parser: Box<dyn Parser>) -> Self {
+        LengthPrefixed { size, parser }
+    }
+}
+
+impl Parser for LengthPrefixed {
+    fn parse<'s>(&mut self, ctx: &mut Context<'s>) -> Result<ParseData, ParsingError<'s>> {
+        let input = ctx.data;
+        let size = self.size as usize;
+        if input.len() < size {
+            return Err(ParsingError::TooShort);
+        }
+        let length = input[..size]
+            .iter()
+            .fold(0usize, |acc, byte| (acc << 8) + *byte as usize);
+        let rest = &input[size..];
+        if rest.len() < length {
+            return Err(ParsingError::TooShort);
+        }
+
+        ctx.set(&rest[..length]);
+        let data = self.parser.parse(ctx)?;
+        if !ctx.data.is_empty() {
+            return Err(ParsingError::UnexpectedLength(length));
+        }
+        ctx.set(&rest[length..]);
+        Ok(data)
+    }
+}
+
+///Runs parsers in sequence, into a [ParseData::Record] of their named data.
+///Errors name the failed field.
+#[derive(Default)]
+pub struct Record {
+    fields: Vec<(String, Box<dyn Parser>)>,
+}
+
+impl Record {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_field(mut self, name: &str, parser: Box<dyn Parser>) -> Self {
+        self.fields.push((name.to_string(), parser));
+        self
+    }
+}
+
+impl Parser for Record {
+    fn parse<'s>(&mut self, ctx: &mut Context<'s>) -> Result<ParseData, ParsingError<'s>> {
+        self.fields
+            .iter_mut()
+            .map(|(name, parser)| match parser.parse(ctx) {
+                Ok(data) => Ok((name.clone(), data)),
+                Err(err) => Err(ParsingError::Part(name.clone(), Box::new(err))),
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(ParseData::Record)
+    }
+}
+
 #[cfg(test)]
 mod scratch {
     use super::*;
@@ -1232,4 +1509,100 @@ mod tests {
             panic!("expecting ParseData::U64, got something else!");
         }
     }
+
+    #[test]
+    fn repeat_until_end_parses_every_block() {
+        let block = b"\x01\x01\x01\x02\x01\x00\x11\x22\x33\x44\x55";
+        let input = [&block[..], &block[..]].concat();
+        let mut ctx = Context::new(&input);
+        let mut parser = Repeat::until_end(Box::new(Connections::new()));
+        let info = ParseData::Connections(PerPortInfo {
+            interface: 1,
+            port: 2,
+            macs: vec![MacAddr6::new(0, 0x11, 0x22, 0x33, 0x44, 0x55)],
+        });
+        assert_eq!(
+            parser.parse(&mut ctx),
+            Ok(ParseData::List(vec![info.clone(), info]))
+        );
+        assert!(ctx.data.is_empty());
+    }
+
+    #[test]
+    fn repeat_names_the_failed_item() {
+        let mut ctx = Context::new(b"\x01\x10\x01\x20\x01\x65");
+        let mut parser = Repeat::times(3, Box::new(Percentage::new()));
+        assert_eq!(
+            parser.parse(&mut ctx),
+            Err(ParsingError::Part(
+                "item 2".to_string(),
+                Box::new(ParsingError::InvalidPercentage(0x65))
+            ))
+        );
+        let mut ctx = Context::new(b"\x01\x10\x01\x20\x01\x30");
+        let mut parser = Repeat::times(2, Box::new(Percentage::new()));
+        assert_eq!(
+            parser.parse(&mut ctx),
+            Ok(ParseData::List(vec![
+                ParseData::U32(0x10),
+                ParseData::U32(0x20)
+            ]))
+        );
+        assert_eq!(ctx.data.len(), 2);
+    }
+
+    #[test]
+    fn optional_parses_only_remaining_data() {
+        let mut parser = Optional::new(Box::new(Percentage::new()));
+        assert_eq!(parser.parse(&mut Context::new(b"")), Ok(ParseData::Null));
+        assert_eq!(
+            parser.parse(&mut Context::new(b"\x01\x32")),
+            Ok(ParseData::U32(50))
+        );
+    }
+
+    #[test]
+    fn choice_picks_the_parser_of_the_tag() {
+        let mut parser = Choice::new()
+            .with_option(1, "name", Box::new(SizedText::new(31)))
+            .with_option(2, "load", Box::new(Percentage::new()));
+        assert_eq!(
+            parser.parse(&mut Context::new(b"\x01\x02ab")),
+            Ok(ParseData::Record(vec![(
+                "name".to_string(),
+                ParseData::Text("ab".to_string())
+            )]))
+        );
+        assert_eq!(
+            parser.parse(&mut Context::new(b"\x09\x01\x32")),
+            Err(ParsingError::UnknownTag(9))
+        );
+    }
+
+    #[test]
+    fn length_prefixed_data_is_used_up() {
+        let mut parser = LengthPrefixed::new(NumberSize::Two, Box::new(Percentage::new()));
+        let mut ctx = Context::new(b"\x00\x02\x01\x32\xff");
+        assert_eq!(parser.parse(&mut ctx), Ok(ParseData::U32(50)));
+        assert_eq!(ctx.data, b"\xff");
+        let mut ctx = Context::new(b"\x00\x03\x01\x32\xff");
+        assert_eq!(
+            parser.parse(&mut ctx),
+            Err(ParsingError::UnexpectedLength(3))
+        );
+    }
+
+    #[test]
+    fn record_errors_name_the_failed_field() {
+        let mut parser = Record::new()
+            .with_field("load", Box::new(Percentage::new()))
+            .with_field(
+                "ports",
+                Box::new(Repeat::times(2, Box::new(Percentage::new()))),
+            );
+        let error = parser
+            .parse(&mut Context::new(b"\x01\x32\x01\x10\x01"))
+            .unwrap_err();
+        assert_eq!(error.to_string(), "ports: item 1: Not enough data");
+    }
 }
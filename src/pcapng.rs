@@ -0,0 +1,499 @@
+use std::convert::TryInto;
+use std::fmt;
+use std::io::{self, Read};
+use std::time::Duration;
+
+pub(crate) const SECTION_HEADER: u32 = 0x0A0D_0D0A;
+pub(crate) const INTERFACE_DESCRIPTION: u32 = 0x0000_0001;
+const SIMPLE_PACKET: u32 = 0x0000_0003;
+pub(crate) const ENHANCED_PACKET: u32 = 0x0000_0006;
+pub(crate) const BYTE_ORDER_MAGIC: u32 = 0x1A2B_3C4D;
+
+pub(crate) const OPT_END: u16 = 0;
+pub(crate) const OPT_COMMENT: u16 = 1;
+pub(crate) const IF_NAME: u16 = 2;
+const IF_DESCRIPTION: u16 = 3;
+pub(crate) const IF_TSRESOL: u16 = 9;
+
+/// Largest block accepted, as declared by its length field
+const MAX_BLOCK: usize = 16 * 1024 * 1024;
+
+/// Checks if `data` starts like a pcapng file
+pub fn is_pcapng(data: &[u8]) -> bool {
+    data.len() >= 4 && data[..4] == SECTION_HEADER.to_le_bytes()
+}
+
+/// Errors that may occur while reading a pcapng file
+#[derive(Debug)]
+pub enum PcapngError {
+    /// Reading from the underlying reader failed
+    Io(io::Error),
+    /// The file does not start with a section header block
+    NotPcapng,
+    /// A block is shorter than its type requires, or its length is invalid
+    /// or over 16 MiB
+    InvalidBlock(u32),
+    /// A packet refers to an interface that was never described
+    UnknownInterface(u32),
+}
+
+impl fmt::Display for PcapngError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PcapngError::Io(err) => write!(f, "io error: {}", err),
+            PcapngError::NotPcapng => write!(f, "not a pcapng file"),
+            PcapngError::InvalidBlock(btype) => write!(f, "invalid block of type 0x{:x}", btype),
+            PcapngError::UnknownInterface(id) => write!(f, "unknown interface id {}", id),
+        }
+    }
+}
+
+impl From<io::Error> for PcapngError {
+    fn from(err: io::Error) -> Self {
+        PcapngError::Io(err)
+    }
+}
+
+/// An interface, as described by an interface description block
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Interface {
+    /// Link layer type (1 is ethernet)
+    pub link_type: u16,
+    /// Maximum number of bytes captured from each packet (0 is unlimited)
+    pub snaplen: u32,
+    /// Interface name (if_name option)
+    pub name: Option<String>,
+    /// Interface description (if_description option)
+    pub description: Option<String>,
+    /// Comments attached to the interface
+    pub comments: Vec<String>,
+    //timestamp units per second
+    ts_units: u64,
+}
+
+impl Interface {
+    fn timestamp(&self, raw: u64) -> Duration {
+        let secs = raw / self.ts_units;
+        let nanos = (raw % self.ts_units) as u128 * 1_000_000_000 / self.ts_units as u128;
+        Duration::new(secs, nanos as u32)
+    }
+}
+
+/// A packet read from a pcapng file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Packet {
+    /// Index of the interface (in the current section) the packet was captured on
+    pub interface_id: u32,
+    /// Capture timestamp, since the unix epoch
+    pub timestamp: Duration,
+    /// Original length of the packet on the wire
+    pub len: u32,
+    /// The captured bytes
+    pub data: Vec<u8>,
+    /// Comments attached to the packet
+    pub comments: Vec<String>,
+}
+
+/// Options of a block, as (code, value) pairs
+type Options = Vec<(u16, Vec<u8>)>;
+
+/// Reads the packets of a pcapng file, one block at a time. Unknown block
+/// types are skipped.
+pub struct PcapngReader<R> {
+    reader: R,
+    big_endian: bool,
+    interfaces: Vec<Interface>,
+    section_comments: Vec<String>,
+}
+
+impl<R: Read> PcapngReader<R> {
+    /// Create a new reader, checking the leading section header block
+    pub fn new(mut reader: R) -> Result<Self, PcapngError> {
+        let mut btype = [0u8; 4];
+        reader.read_exact(&mut btype)?;
+        if u32::from_le_bytes(btype) != SECTION_HEADER {
+            return Err(PcapngError::NotPcapng);
+        }
+        let mut instance = PcapngReader {
+            reader,
+            big_endian: false,
+            interfaces: vec![],
+            section_comments: vec![],
+        };
+        instance.read_section_header()?;
+        Ok(instance)
+    }
+
+    /// Interfaces described so far in the current section
+    pub fn interfaces(&self) -> &[Interface] {
+        &self.interfaces
+    }
+
+    /// The interface with the given id in the current section
+    pub fn interface(&self, id: u32) -> Option<&Interface> {
+        self.interfaces.get(id as usize)
+    }
+
+    /// Comments attached to the current section
+    pub fn section_comments(&self) -> &[String] {
+        &self.section_comments
+    }
+
+    fn u16(&self, bytes: &[u8]) -> u16 {
+        let bytes = bytes[..2].try_into().unwrap();
+        if self.big_endian {
+            u16::from_be_bytes(bytes)
+        } else {
+            u16::from_le_bytes(bytes)
+        }
+    }
+
+    fn u32(&self, bytes: &[u8]) -> u32 {
+        let bytes = bytes[..4].try_into().unwrap();
+        if self.big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        }
+    }
+
+    /// Read the rest of a section header block (the type is already consumed)
+    fn read_section_header(&mut self) -> Result<(), PcapngError> {
+        let mut head = [0u8; 8];
+        self.reader.read_exact(&mut head)?;
+        //the byte order magic decides the endianness of everything else
+        self.big_endian = match u32::from_le_bytes(head[4..].try_into().unwrap()) {
+            BYTE_ORDER_MAGIC => false,
+            magic if magic.swap_bytes() == BYTE_ORDER_MAGIC => true,
+            _ => return Err(PcapngError::NotPcapng),
+        };
+        let total = self.u32(&head) as usize;
+        if !(28..=MAX_BLOCK).contains(&total) || total & 3 != 0 {
+            return Err(PcapngError::InvalidBlock(SECTION_HEADER));
+        }
+        //body after the byte order magic, plus the trailing length
+        let body = self.read_exact(total - 12)?;
+        //skip version (4 bytes) and section length (8 bytes)
+        let options = self.options(&body[12..total - 16]);
+        self.section_comments = comments(&options);
+        self.interfaces.clear();
+        Ok(())
+    }
+
+    /// Read the next block as (type, body), None at the end of the file
+    fn read_block(&mut self) -> Result<Option<(u32, Vec<u8>)>, PcapngError> {
+        let mut head = [0u8; 8];
+        match self.reader.read_exact(&mut head[..4]) {
+            Ok(()) => (),
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(err) => return Err(err.into()),
+        }
+        if u32::from_le_bytes(head[..4].try_into().unwrap()) == SECTION_HEADER {
+            self.read_section_header()?;
+            return Ok(Some((SECTION_HEADER, vec![])));
+        }
+        self.reader.read_exact(&mut head[4..])?;
+        let btype = self.u32(&head);
+        let total = self.u32(&head[4..]) as usize;
+        if !(12..=MAX_BLOCK).contains(&total) || total & 3 != 0 {
+            return Err(PcapngError::InvalidBlock(btype));
+        }
+        let mut body = self.read_exact(total - 8)?;
+        //drop the trailing length
+        body.truncate(total - 12);
+        Ok(Some((btype, body)))
+    }
+
+    /// Read `size` bytes, growing the buffer as they arrive rather than
+    /// trusting a declared size upfront
+    fn read_exact(&mut self, size: usize) -> Result<Vec<u8>, PcapngError> {
+        let mut data = vec![];
+        (&mut self.reader)
+            .take(size as u64)
+            .read_to_end(&mut data)?;
+        if data.len() < size {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+        Ok(data)
+    }
+
+    fn options(&self, mut data: &[u8]) -> Options {
+        let mut result = vec![];
+        while data.len() >= 4 {
+            let code = self.u16(data);
+            let len = self.u16(&data[2..]) as usize;
+            if code == OPT_END || data.len() < 4 + len {
+                break;
+            }
+            result.push((code, data[4..4 + len].to_vec()));
+            //values are padded to 32 bits
+            let padded = (len + 3) & !3;
+            data = data.get(4 + padded..).unwrap_or(&[]);
+        }
+        result
+    }
+
+    fn interface_description(&self, body: &[u8]) -> Result<Interface, PcapngError> {
+        if body.len() < 8 {
+            return Err(PcapngError::InvalidBlock(INTERFACE_DESCRIPTION));
+        }
+        let options = self.options(&body[8..]);
+        let text = |code| {
+            options
+                .iter()
+                .find(|(opt, _)| *opt == code)
+                .map(|(_, value)| String::from_utf8_lossy(value).into_owned())
+        };
+        //the default resolution is microseconds
+        let ts_units = match options.iter().find(|(opt, _)| *opt == IF_TSRESOL) {
+            Some((_, value)) if !value.is_empty() => {
+                let exp = u32::from(value[0] & 0x7f);
+                let base: u64 = if value[0] & 0x80 == 0 { 10 } else { 2 };
+                base.checked_pow(exp).unwrap_or(1_000_000)
+            }
+            _ => 1_000_000,
+        };
+        Ok(Interface {
+            link_type: self.u16(body),
+            snaplen: self.u32(&body[4..]),
+            name: text(IF_NAME),
+            description: text(IF_DESCRIPTION),
+            comments: comments(&options),
+            ts_units,
+        })
+    }
+
+    fn enhanced_packet(&self, body: &[u8]) -> Result<Packet, PcapngError> {
+        if body.len() < 20 {
+            return Err(PcapngError::InvalidBlock(ENHANCED_PACKET));
+        }
+        let interface_id = self.u32(body);
+        let interface = self
+            .interface(interface_id)
+            .ok_or(PcapngError::UnknownInterface(interface_id))?;
+        let raw_ts = (u64::from(self.u32(&body[4..])) << 32) + u64::from(self.u32(&body[8..]));
+        let caplen = self.u32(&body[12..]) as usize;
+        let padded = (caplen + 3) & !3;
+        let data = body
+            .get(20..20 + caplen)
+            .ok_or(PcapngError::InvalidBlock(ENHANCED_PACKET))?;
+        let options = self.options(body.get(20 + padded..).unwrap_or(&[]));
+        Ok(Packet {
+            interface_id,
+            timestamp: interface.timestamp(raw_ts),
+            len: self.u32(&body[16..]),
+            data: data.to_vec(),
+            comments: comments(&options),
+        })
+    }
+
+    fn simple_packet(&self, body: &[u8]) -> Result<Packet, PcapngError> {
+        if body.len() < 4 {
+            return Err(PcapngError::InvalidBlock(SIMPLE_PACKET));
+        }
+        let interface = self.interface(0).ok_or(PcapngError::UnknownInterface(0))?;
+        let len = self.u32(body);
+        //captured length is the smaller of the original length and snaplen
+        let mut caplen = (len as usize).min(body.len() - 4);
+        if interface.snaplen != 0 {
+            caplen = caplen.min(interface.snaplen as usize);
+        }
+        Ok(Packet {
+            interface_id: 0,
+            timestamp: Duration::default(),
+            len,
+            data: body[4..4 + caplen].to_vec(),
+            comments: vec![],
+        })
+    }
+}
+
+impl<R: Read> Iterator for PcapngReader<R> {
+    type Item = Result<Packet, PcapngError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (btype, body) = match self.read_block() {
+                Ok(Some(block)) => block,
+                Ok(None) => return None,
+                Err(err) => return Some(Err(err)),
+            };
+            match btype {
+                INTERFACE_DESCRIPTION => match self.interface_description(&body) {
+                    Ok(interface) => self.interfaces.push(interface),
+                    Err(err) => return Some(Err(err)),
+                },
+                ENHANCED_PACKET => return Some(self.enhanced_packet(&body)),
+                SIMPLE_PACKET => return Some(self.simple_packet(&body)),
+                //section headers are handled by read_block, skip everything else
+                _ => (),
+            }
+        }
+    }
+}
+
+fn comments(options: &[(u16, Vec<u8>)]) -> Vec<String> {
+    options
+        .iter()
+        .filter(|(code, _)| *code == OPT_COMMENT)
+        .map(|(_, value)| String::from_utf8_lossy(value).into_owned())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(btype: u32, body: &[u8]) -> Vec<u8> {
+        let total = (12 + body.len()) as u32;
+        let mut result = btype.to_le_bytes().to_vec();
+        result.extend(&total.to_le_bytes());
+        result.extend(body);
+        result.extend(&total.to_le_bytes());
+        result
+    }
+
+    fn option(code: u16, value: &[u8]) -> Vec<u8> {
+        let mut result = code.to_le_bytes().to_vec();
+        result.extend(&(value.len() as u16).to_le_bytes());
+        result.extend(value);
+        result.resize(result.len() + (4 - value.len() % 4) % 4, 0);
+        result
+    }
+
+    fn section_header(options: &[u8]) -> Vec<u8> {
+        let mut body = BYTE_ORDER_MAGIC.to_le_bytes().to_vec();
+        body.extend(&[1, 0, 0, 0]);
+        body.extend(&(-1i64).to_le_bytes());
+        body.extend(options);
+        block(SECTION_HEADER, &body)
+    }
+
+    fn interface(options: &[u8]) -> Vec<u8> {
+        let mut body = vec![1, 0, 0, 0];
+        body.extend(&0u32.to_le_bytes());
+        body.extend(options);
+        block(INTERFACE_DESCRIPTION, &body)
+    }
+
+    fn enhanced_packet(interface_id: u32, ts: u64, data: &[u8], options: &[u8]) -> Vec<u8> {
+        let mut body = interface_id.to_le_bytes().to_vec();
+        body.extend(&((ts >> 32) as u32).to_le_bytes());
+        body.extend(&(ts as u32).to_le_bytes());
+        body.extend(&(data.len() as u32).to_le_bytes());
+        body.extend(&(data.len() as u32).to_le_bytes());
+        body.extend(data);
+        body.resize(body.len() + (4 - data.len() % 4) % 4, 0);
+        body.extend(options);
+        block(ENHANCED_PACKET, &body)
+    }
+
+    #[test]
+    fn detects_pcapng_magic() {
+        assert!(is_pcapng(&section_header(&[])));
+        assert!(!is_pcapng(b"\xd4\xc3\xb2\xa1"));
+        assert!(!is_pcapng(b""));
+    }
+
+    #[test]
+    fn rejects_non_pcapng_input() {
+        let input: &[u8] = b"\xd4\xc3\xb2\xa1\x02\x00\x04\x00";
+        match PcapngReader::new(input) {
+            Err(PcapngError::NotPcapng) => (),
+            _ => panic!("a pcap header is not pcapng!"),
+        }
+    }
+
+    #[test]
+    fn reads_interfaces_packets_and_comments() {
+        let mut file = section_header(&option(OPT_COMMENT, b"field capture"));
+        file.extend(interface(&option(IF_NAME, b"eth0")));
+        file.extend(interface(
+            &[option(IF_NAME, b"wlan0"), option(IF_TSRESOL, &[9])].concat(),
+        ));
+        file.extend(enhanced_packet(0, 1_500_000, b"abcde", &[]));
+        file.extend(enhanced_packet(
+            1,
+            2_000_000_001,
+            b"abcd",
+            &option(OPT_COMMENT, b"broken"),
+        ));
+
+        let mut reader = PcapngReader::new(&file[..]).unwrap();
+        assert_eq!(reader.section_comments(), &["field capture".to_string()]);
+
+        let first = reader.next().unwrap().unwrap();
+        assert_eq!(first.interface_id, 0);
+        assert_eq!(first.timestamp, Duration::from_millis(1500));
+        assert_eq!(first.data, b"abcde");
+        assert_eq!(first.len, 5);
+        assert!(first.comments.is_empty());
+
+        let second = reader.next().unwrap().unwrap();
+        assert_eq!(second.interface_id, 1);
+        assert_eq!(second.timestamp, Duration::new(2, 1));
+        assert_eq!(second.data, b"abcd");
+        assert_eq!(second.comments, vec!["broken".to_string()]);
+
+        assert!(reader.next().is_none());
+        assert_eq!(reader.interfaces().len(), 2);
+        assert_eq!(reader.interface(0).unwrap().name.as_deref(), Some("eth0"));
+        assert_eq!(reader.interface(1).unwrap().name.as_deref(), Some("wlan0"));
+    }
+
+    #[test]
+    fn packet_for_unknown_interface_fails() {
+        let mut file = section_header(&[]);
+        file.extend(enhanced_packet(3, 0, b"abcd", &[]));
+
+        let mut reader = PcapngReader::new(&file[..]).unwrap();
+        match reader.next() {
+            Some(Err(PcapngError::UnknownInterface(3))) => (),
+            _ => panic!("interface 3 was never described!"),
+        }
+    }
+
+    #[test]
+    fn new_section_resets_interfaces() {
+        let mut file = section_header(&[]);
+        file.extend(interface(&[]));
+        file.extend(section_header(&[]));
+        file.extend(enhanced_packet(0, 0, b"abcd", &[]));
+
+        let mut reader = PcapngReader::new(&file[..]).unwrap();
+        match reader.next() {
+            Some(Err(PcapngError::UnknownInterface(0))) => (),
+            _ => panic!("interfaces belong to their section!"),
+        }
+    }
+
+    #[test]
+    fn oversized_block_is_an_error() {
+        let mut file = section_header(&[]);
+        //an interface block declaring 4 GiB
+        file.extend(&INTERFACE_DESCRIPTION.to_le_bytes());
+        file.extend(&0xffff_fffcu32.to_le_bytes());
+        file.extend(&[0; 8]);
+
+        let mut reader = PcapngReader::new(&file[..]).unwrap();
+        match reader.next() {
+            Some(Err(PcapngError::InvalidBlock(INTERFACE_DESCRIPTION))) => (),
+            _ => panic!("the block is too large!"),
+        }
+    }
+
+    #[test]
+    fn truncated_block_is_an_error() {
+        let mut file = section_header(&[]);
+        file.extend(interface(&[]));
+        let mut packet = enhanced_packet(0, 0, b"abcd", &[]);
+        packet.truncate(packet.len() - 6);
+        file.extend(packet);
+
+        let mut reader = PcapngReader::new(&file[..]).unwrap();
+        match reader.next() {
+            Some(Err(PcapngError::Io(_))) => (),
+            _ => panic!("the last block is incomplete!"),
+        }
+    }
+}
@@ -0,0 +1,302 @@
+use crate::linters::maker_code;
+use crate::{FrameInfo, Lint, ParsingError, Severity};
+use macaddr::MacAddr6;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Errors while loading a lint policy
+#[derive(Debug)]
+pub enum PolicyError {
+    Io(io::Error),
+    /// The line (starting from 1) is not a valid rule
+    InvalidLine(usize, String),
+}
+
+impl fmt::Display for PolicyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PolicyError::Io(err) => write!(f, "{}", err),
+            PolicyError::InvalidLine(line, reason) => write!(f, "line {}: {}", line, reason),
+        }
+    }
+}
+
+impl From<io::Error> for PolicyError {
+    fn from(err: io::Error) -> Self {
+        PolicyError::Io(err)
+    }
+}
+
+/// Frames a suppression applies to
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Target {
+    Source(MacAddr6),
+    MakerCode(String),
+}
+
+/// Which lints are reported, and how seriously.
+///
+/// A policy can be built from code, or read from a config file with one
+/// rule per line; empty lines & lines starting with `#` are ignored:
+///
+/// ```text
+/// # unknown tlvs are fine
+/// disable W3
+/// # promote/demote
+/// severity W1 error
+/// severity E4 warning
+/// # known vendor quirks
+/// suppress W2 maker ABCDEF
+/// suppress E3 source 00:11:22:33:44:55
+/// ```
+///
+/// The policy of a dispatcher is applied by [Dispatcher::parse()], and
+/// again by [Dispatcher::finish()] after the capture metadata of a frame is
+/// attached, so that suppressions by source & lints of the packet are
+/// covered. In strict mode, the parsing errors of the lints it drops are
+/// dropped as well.
+///
+/// # Examples
+///
+/// ```
+/// use rust_htip::policy::LintPolicy;
+/// use rust_htip::{Dispatcher, Lint, Severity};
+///
+/// let policy = LintPolicy::new()
+///     .disable(Lint::Warning(3))
+///     .severity(Lint::Warning(1), Severity::Error)
+///     .suppress_maker_code(Lint::Warning(2), "ABCDEF");
+/// let mut dispatcher = Dispatcher::new();
+/// dispatcher.set_policy(policy);
+/// ```
+#[derive(Debug, Default, Clone)]
+pub struct LintPolicy {
+    disabled: HashSet<Lint>,
+    severities: HashMap<Lint, Severity>,
+    suppressions: Vec<(Lint, Target)>,
+}
+
+impl LintPolicy {
+    /// A policy that reports every lint with its own severity
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Never report `lint`
+    pub fn disable(mut self, lint: Lint) -> Self {
+        self.disabled.insert(lint);
+        self
+    }
+
+    /// Report `lint` with `severity`, instead of its own
+    pub fn severity(mut self, lint: Lint, severity: Severity) -> Self {
+        self.severities.insert(lint, severity);
+        self
+    }
+
+    /// Don't report `lint` for frames sent from `source`
+    pub fn suppress_source(mut self, lint: Lint, source: MacAddr6) -> Self {
+        self.suppressions.push((lint, Target::Source(source)));
+        self
+    }
+
+    /// Don't report `lint` for frames advertising maker code `code`
+    pub fn suppress_maker_code(mut self, lint: Lint, code: &str) -> Self {
+        self.suppressions
+            .push((lint, Target::MakerCode(code.to_ascii_uppercase())));
+        self
+    }
+
+    pub fn from_config(config: &str) -> Result<Self, PolicyError> {
+        config
+            .lines()
+            .enumerate()
+            .map(|(index, line)| (index + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+            .try_fold(LintPolicy::new(), |policy, (index, line)| {
+                policy
+                    .with_rule(line)
+                    .map_err(|reason| PolicyError::InvalidLine(index, reason))
+            })
+    }
+
+    /// Load a config file from `path`
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, PolicyError> {
+        LintPolicy::from_config(&fs::read_to_string(path)?)
+    }
+
+    fn with_rule(self, rule: &str) -> Result<Self, String> {
+        let words = rule.split_whitespace().collect::<Vec<_>>();
+        match words.as_slice() {
+            ["disable", lint] => Ok(self.disable(lint.parse()?)),
+            ["severity", lint, severity] => Ok(self.severity(lint.parse()?, severity.parse()?)),
+            ["suppress", lint, "maker", code] => Ok(self.suppress_maker_code(lint.parse()?, code)),
+            ["suppress", lint, "source", mac] => {
+                let mac = mac.parse().map_err(|_| format!("invalid MAC {}", mac))?;
+                Ok(self.suppress_source(lint.parse()?, mac))
+            }
+            _ => Err(format!("unknown rule: {}", rule)),
+        }
+    }
+
+    /// Drop the disabled & suppressed lints of `info`, along with their
+    /// strict mode errors, and set the severity of the rest
+    pub fn apply(&self, info: &mut FrameInfo) {
+        let source = info.packet.as_ref().and_then(|packet| packet.source);
+        let code = maker_code(info.info.iter().map(|(key, data)| (key, data)))
+            .map(str::to_ascii_uppercase);
+        let suppressed = |lint: &Lint| {
+            self.suppressions
+                .iter()
+                .filter(|(suppressed, _)| suppressed == lint)
+                .any(|(_, target)| match target {
+                    Target::Source(mac) => source == Some(*mac),
+                    Target::MakerCode(maker) => code.as_ref() == Some(maker),
+                })
+        };
+
+        let dropped = |lint: &Lint| self.disabled.contains(lint) || suppressed(lint);
+
+        info.lints.retain(|entry| !dropped(&entry.lint));
+        info.errors.retain(|(_, error)| match error {
+            ParsingError::Lint(lint) => !dropped(lint),
+            _ => true,
+        });
+        for entry in info.lints.iter_mut() {
+            entry.severity = self
+                .severities
+                .get(&entry.lint)
+                .copied()
+                .unwrap_or_else(|| entry.lint.severity());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Dispatcher, Mode, PacketInfo};
+    use std::time::Duration;
+
+    //mandatory tlvs, maker code CAFEBE with trailing data, no end tlv
+    const FRAME: &[u8] = b"\x02\x07\x04ABCDEF\x04\x07\x03ABCDEF\x06\x02\x00\x78\
+        \xfe\x0e\xe0\x27\x1a\x01\x02\x06CAFEBEex";
+
+    fn lints(policy: LintPolicy, source: Option<MacAddr6>) -> Vec<(Lint, Severity)> {
+        let mut dsp = Dispatcher::new();
+        dsp.set_policy(policy);
+        let mut info = match dsp.parse(FRAME) {
+            Ok(info) => info,
+            Err(_) => panic!("this should parse, check frame!"),
+        };
+        if let Some(source) = source {
+            let packet = PacketInfo::new(0, Duration::from_secs(0), 0, 0).with_source(source);
+            info = info.with_packet(packet);
+        }
+        dsp.finish(&mut info);
+        info.lints
+            .iter()
+            .map(|entry| (entry.lint, entry.severity))
+            .collect()
+    }
+
+    #[test]
+    fn default_policy_reports_everything() {
+        let result = lints(LintPolicy::new(), None);
+        assert!(result.contains(&(Lint::Error(1), Severity::Error)));
+        assert!(result.contains(&(Lint::Warning(2), Severity::Warning)));
+    }
+
+    #[test]
+    fn disabled_lints_are_dropped() {
+        let result = lints(LintPolicy::new().disable(Lint::Error(1)), None);
+        assert!(result.iter().all(|(lint, _)| *lint != Lint::Error(1)));
+    }
+
+    #[test]
+    fn severities_are_overridden() {
+        let policy = LintPolicy::new()
+            .severity(Lint::Warning(2), Severity::Error)
+            .severity(Lint::Error(1), Severity::Warning);
+        let result = lints(policy, None);
+        assert!(result.contains(&(Lint::Error(1), Severity::Warning)));
+        assert!(result.contains(&(Lint::Warning(2), Severity::Error)));
+    }
+
+    #[test]
+    fn lints_are_suppressed_by_maker_code_and_source() {
+        let source = MacAddr6::new(0, 0x11, 0x22, 0x33, 0x44, 0x55);
+        let policy = LintPolicy::new()
+            .suppress_maker_code(Lint::Warning(2), "cafebe")
+            .suppress_source(Lint::Error(1), source);
+        let result = lints(policy.clone(), None);
+        assert!(result.iter().all(|(lint, _)| *lint != Lint::Warning(2)));
+        assert!(result.iter().any(|(lint, _)| *lint == Lint::Error(1)));
+
+        let result = lints(policy, Some(source));
+        assert!(result.iter().all(|(lint, _)| *lint != Lint::Error(1)));
+    }
+
+    #[test]
+    fn parse_applies_the_policy() {
+        let mut dsp = Dispatcher::new();
+        dsp.set_policy(LintPolicy::new().disable(Lint::Error(1)));
+        let info = dsp.parse(FRAME).ok().unwrap();
+        assert!(info.lints.iter().all(|entry| entry.lint != Lint::Error(1)));
+    }
+
+    #[test]
+    fn strict_errors_are_suppressed_by_source() {
+        let source = MacAddr6::new(0, 0x11, 0x22, 0x33, 0x44, 0x55);
+        let mut dsp = Dispatcher::new();
+        dsp.set_mode(Mode::Strict);
+        dsp.set_policy(LintPolicy::new().suppress_source(Lint::Error(1), source));
+        let strict_error = (None, ParsingError::Lint(Lint::Error(1)));
+        let info = dsp.parse(FRAME).ok().unwrap();
+        assert!(info.errors.contains(&strict_error));
+
+        let packet = PacketInfo::new(0, Duration::from_secs(0), 0, 0).with_source(source);
+        let mut info = info.with_packet(packet);
+        dsp.finish(&mut info);
+        assert!(!info.errors.contains(&strict_error));
+    }
+
+    #[test]
+    fn packet_lints_are_covered() {
+        let mut dsp = Dispatcher::new();
+        dsp.set_policy(LintPolicy::new().disable(Lint::Warning(4)));
+        //captured 8 of 100 bytes
+        let packet = PacketInfo::new(0, Duration::from_secs(0), 8, 100);
+        let mut info = dsp.parse(FRAME).ok().unwrap().with_packet(packet);
+        dsp.finish(&mut info);
+        assert!(info
+            .lints
+            .iter()
+            .all(|entry| entry.lint != Lint::Warning(4)));
+    }
+
+    #[test]
+    fn config_is_parsed() {
+        let config = "# comment\n\ndisable W3\nseverity W1 error\n\
+            suppress W2 maker ABCDEF\nsuppress E3 source 00:11:22:33:44:55\n";
+        let policy = LintPolicy::from_config(config).unwrap();
+        assert!(policy.disabled.contains(&Lint::Warning(3)));
+        assert_eq!(
+            policy.severities.get(&Lint::Warning(1)),
+            Some(&Severity::Error)
+        );
+        assert_eq!(policy.suppressions.len(), 2);
+    }
+
+    #[test]
+    fn invalid_config_is_rejected() {
+        match LintPolicy::from_config("disable W3\nenable W3") {
+            Err(PolicyError::InvalidLine(2, _)) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert!(LintPolicy::from_config("severity W1 fatal").is_err());
+    }
+}
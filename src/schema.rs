@@ -0,0 +1,749 @@
+use crate::linters::{LintContext, Linter};
+use crate::parsers::*;
+use crate::typed::{FieldInfo, Layout};
+use crate::{Lint, LintEntry, ParseData, TlvKey};
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use toml::{Table, Value};
+
+/// Errors while loading a schema
+#[derive(Debug)]
+pub enum SchemaError {
+    Io(io::Error),
+    /// The file is not valid TOML
+    Toml(toml::de::Error),
+    /// A tlv definition is not valid
+    Invalid(String),
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchemaError::Io(err) => write!(f, "{}", err),
+            SchemaError::Toml(err) => write!(f, "{}", err),
+            SchemaError::Invalid(reason) => write!(f, "{}", reason),
+        }
+    }
+}
+
+impl From<io::Error> for SchemaError {
+    fn from(err: io::Error) -> Self {
+        SchemaError::Io(err)
+    }
+}
+
+impl From<toml::de::Error> for SchemaError {
+    fn from(err: toml::de::Error) -> Self {
+        SchemaError::Toml(err)
+    }
+}
+
+/// Layout of a field
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum FieldKind {
+    /// Text after its size, up to the maximum size
+    SizedText(usize),
+    /// Text after its size, which must be exactly the size
+    ExactText(usize),
+    /// Number after its size, up to the maximum size in bytes
+    SizedNumber(usize),
+    Percentage,
+    /// MAC addresses after their count
+    MacList,
+    /// Nested fields
+    Composite(Vec<Field>),
+    /// An item repeated a number of times, or until no data is left
+    Repeat(Option<usize>, Box<Field>),
+    /// An item, if any data is left
+    Optional(Box<Field>),
+    /// An item after its length, a number of this many bytes
+    LengthPrefixed(usize, Box<Field>),
+    /// One of the fields, picked by the tag byte in front of them
+    Choice(Vec<(u8, Field)>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Field {
+    name: String,
+    kind: FieldKind,
+    /// The characters allowed in a text field, if restricted
+    charset: Option<String>,
+}
+
+impl Field {
+    /// The field of a Rust type, see [crate::typed]
+    fn typed(info: &FieldInfo) -> Self {
+        let kind = match info.layout {
+            Layout::SizedText(max) => FieldKind::SizedText(max),
+            Layout::ExactText(size) => FieldKind::ExactText(size),
+            Layout::SizedNumber(size) => FieldKind::SizedNumber(size),
+            Layout::Percentage => FieldKind::Percentage,
+            Layout::MacList => FieldKind::MacList,
+        };
+        let charset = info
+            .charset
+            .map(|charset| expand(charset).expect("invalid charset"));
+        Field {
+            name: info.name.to_string(),
+            kind,
+            charset,
+        }
+    }
+
+    fn from_value(value: &Value) -> Result<Self, String> {
+        let table = value.as_table().ok_or("fields must be tables")?;
+        let name = string(table, "name")?.ok_or("a field needs a name")?;
+        Field::from_table(name, table).map_err(|reason| format!("field {}: {}", name, reason))
+    }
+
+    fn from_table(name: &str, table: &Table) -> Result<Self, String> {
+        let kind = match string(table, "parser")?.ok_or("no parser")? {
+            "sized-text" => FieldKind::SizedText(integer(table, "max", 0, 255)?.unwrap_or(255)),
+            "exact-text" => FieldKind::ExactText(integer(table, "size", 0, 255)?.ok_or("no size")?),
+            "sized-number" => {
+                FieldKind::SizedNumber(integer(table, "size", 1, 8)?.ok_or("no size")?)
+            }
+            "percentage" => FieldKind::Percentage,
+            "mac-list" => FieldKind::MacList,
+            "composite" => FieldKind::Composite(fields(table)?),
+            "repeat" => FieldKind::Repeat(integer(table, "count", 0, 255)?, item(table)?),
+            "optional" => FieldKind::Optional(item(table)?),
+            "length-prefixed" => {
+                FieldKind::LengthPrefixed(integer(table, "size", 1, 4)?.unwrap_or(1), item(table)?)
+            }
+            "choice" => FieldKind::Choice(options(table)?),
+            parser => return Err(format!("unknown parser {}", parser)),
+        };
+        let charset = match (string(table, "charset")?, &kind) {
+            (None, _) => None,
+            (Some(charset), FieldKind::SizedText(_)) | (Some(charset), FieldKind::ExactText(_)) => {
+                Some(expand(charset)?)
+            }
+            (Some(_), _) => return Err("charset of a non-text field".to_string()),
+        };
+        Ok(Field {
+            name: name.to_string(),
+            kind,
+            charset,
+        })
+    }
+
+    fn parser(&self) -> Box<dyn Parser> {
+        match &self.kind {
+            FieldKind::SizedText(max) => Box::new(SizedText::new(*max)),
+            FieldKind::ExactText(size) => Box::new(SizedText::exact(*size)),
+            FieldKind::SizedNumber(size) => Box::new(SizedNumber::new(number_size(*size))),
+            FieldKind::Percentage => Box::new(Percentage::new()),
+            FieldKind::MacList => Box::new(Mac::new()),
+            FieldKind::Composite(fields) => record(fields),
+            FieldKind::Repeat(Some(count), item) => Box::new(Repeat::times(*count, item.parser())),
+            FieldKind::Repeat(None, item) => Box::new(Repeat::until_end(item.parser())),
+            FieldKind::Optional(item) => Box::new(Optional::new(item.parser())),
+            FieldKind::LengthPrefixed(size, item) => {
+                Box::new(LengthPrefixed::new(number_size(*size), item.parser()))
+            }
+            FieldKind::Choice(options) => {
+                Box::new(options.iter().fold(Choice::new(), |choice, (tag, field)| {
+                    choice.with_option(*tag, &field.name, field.parser())
+                }))
+            }
+        }
+    }
+
+    /// Add the charset of this field & its nested fields at `path` to `checks`
+    fn charsets(&self, path: Vec<String>, key: &TlvKey, checks: &mut Vec<CharsetCheck>) {
+        if let Some(allowed) = &self.charset {
+            checks.push(CharsetCheck {
+                key: key.clone(),
+                path: path.clone(),
+                allowed: allowed.clone(),
+            });
+        }
+        //items are not named in records, unlike nested fields & options
+        let nested = match &self.kind {
+            FieldKind::Composite(fields) => fields.iter().collect(),
+            FieldKind::Choice(options) => options.iter().map(|(_, field)| field).collect(),
+            FieldKind::Repeat(_, item)
+            | FieldKind::Optional(item)
+            | FieldKind::LengthPrefixed(_, item) => {
+                return item.charsets(path, key, checks);
+            }
+            _ => vec![],
+        };
+        for field in nested {
+            let mut path = path.clone();
+            path.push(field.name.clone());
+            field.charsets(path, key, checks);
+        }
+    }
+}
+
+/// A parser of `fields` into a record
+fn record(fields: &[Field]) -> Box<dyn Parser> {
+    Box::new(fields.iter().fold(Record::new(), |record, field| {
+        record.with_field(&field.name, field.parser())
+    }))
+}
+
+fn number_size(size: usize) -> NumberSize {
+    match size {
+        1 => NumberSize::One,
+        2 => NumberSize::Two,
+        3 => NumberSize::Three,
+        4 => NumberSize::Four,
+        5 => NumberSize::Five,
+        6 => NumberSize::Six,
+        7 => NumberSize::Seven,
+        _ => NumberSize::Eight,
+    }
+}
+
+/// The string value of `key`, if any
+fn string<'t>(table: &'t Table, key: &str) -> Result<Option<&'t str>, String> {
+    match table.get(key) {
+        None => Ok(None),
+        Some(Value::String(value)) => Ok(Some(value)),
+        Some(_) => Err(format!("{} must be a string", key)),
+    }
+}
+
+/// The integer value of `key` between `min` & `max`, if any
+fn integer(table: &Table, key: &str, min: i64, max: i64) -> Result<Option<usize>, String> {
+    match table.get(key) {
+        None => Ok(None),
+        Some(Value::Integer(value)) if (min..=max).contains(value) => Ok(Some(*value as usize)),
+        Some(_) => Err(format!("{} must be a number from {} to {}", key, min, max)),
+    }
+}
+
+/// The fields of `table`, with unique names
+fn fields(table: &Table) -> Result<Vec<Field>, String> {
+    let fields = match table.get("fields") {
+        Some(Value::Array(fields)) if !fields.is_empty() => fields
+            .iter()
+            .map(Field::from_value)
+            .collect::<Result<Vec<_>, _>>()?,
+        _ => return Err("fields must be a non-empty array".to_string()),
+    };
+    let mut names = HashSet::new();
+    match fields.iter().find(|field| !names.insert(&field.name)) {
+        Some(field) => Err(format!("duplicate field {}", field.name)),
+        None => Ok(fields),
+    }
+}
+
+/// The `item` field of `table`, named item unless named otherwise
+fn item(table: &Table) -> Result<Box<Field>, String> {
+    let item = match table.get("item") {
+        Some(Value::Table(item)) => item,
+        _ => return Err("item must be a table".to_string()),
+    };
+    let name = string(item, "name")?.unwrap_or("item");
+    let field = Field::from_table(name, item).map_err(|reason| format!("item: {}", reason))?;
+    Ok(Box::new(field))
+}
+
+/// The `options` fields of `table` & their tags, with unique tags
+fn options(table: &Table) -> Result<Vec<(u8, Field)>, String> {
+    let options = match table.get("options") {
+        Some(Value::Array(options)) if !options.is_empty() => options,
+        _ => return Err("options must be a non-empty array".to_string()),
+    };
+    let mut tags = HashSet::new();
+    options
+        .iter()
+        .map(|option| {
+            let field = Field::from_value(option)?;
+            let tag = option
+                .as_table()
+                .map(|option| integer(option, "tag", 0, 255))
+                .transpose()?
+                .flatten()
+                .ok_or_else(|| format!("option {}: no tag", field.name))?;
+            if !tags.insert(tag) {
+                return Err(format!("duplicate tag {}", tag));
+            }
+            Ok((tag as u8, field))
+        })
+        .collect()
+}
+
+/// The characters of a charset, where `a-z` is a range of characters and a
+/// `-` at the start or end is itself
+fn expand(charset: &str) -> Result<String, String> {
+    let chars = charset.chars().collect::<Vec<_>>();
+    let mut allowed = String::new();
+    let mut index = 0;
+    while index < chars.len() {
+        match chars.get(index..index + 3) {
+            Some(&[from, '-', to]) if from <= to => {
+                allowed.extend(from..=to);
+                index += 3;
+            }
+            Some(&[from, '-', to]) => return Err(format!("invalid range {}-{}", from, to)),
+            _ => {
+                allowed.push(chars[index]);
+                index += 1;
+            }
+        }
+    }
+    Ok(allowed)
+}
+
+/// Bytes from hexadecimal digits, optionally separated by `:` or spaces
+fn hex(text: &str) -> Result<Vec<u8>, String> {
+    let digits = text
+        .chars()
+        .filter(|c| *c != ':' && !c.is_whitespace())
+        .collect::<Vec<_>>();
+    if digits.len() % 2 != 0 {
+        return Err(format!("odd number of digits in prefix {}", text));
+    }
+    digits
+        .chunks(2)
+        .map(|pair| {
+            u8::from_str_radix(&pair.iter().collect::<String>(), 16)
+                .map_err(|_| format!("invalid prefix {}", text))
+        })
+        .collect()
+}
+
+/// The layout of the tlvs matching a key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TlvDefinition {
+    pub name: String,
+    /// The tlv type & the value prefix (e.g. OUI, subtype & info ID) the
+    /// layout applies to
+    pub key: TlvKey,
+    fields: Vec<Field>,
+}
+
+impl TlvDefinition {
+    /// The definition of a Rust type, see [crate::typed]
+    pub(crate) fn typed(name: &str, key: TlvKey, fields: &[FieldInfo]) -> Self {
+        TlvDefinition {
+            name: name.to_string(),
+            key,
+            fields: fields.iter().map(Field::typed).collect(),
+        }
+    }
+
+    fn from_value(value: &Value) -> Result<Self, String> {
+        let table = value.as_table().ok_or("tlv definitions must be tables")?;
+        let name = string(table, "name")?.ok_or("a tlv needs a name")?;
+        TlvDefinition::from_table(name, table).map_err(|reason| format!("tlv {}: {}", name, reason))
+    }
+
+    fn from_table(name: &str, table: &Table) -> Result<Self, String> {
+        let tlv_type = integer(table, "type", 0, 127)?.unwrap_or(127);
+        let prefix = match string(table, "prefix")? {
+            Some(prefix) => hex(prefix)?,
+            None => vec![],
+        };
+        Ok(TlvDefinition {
+            name: name.to_string(),
+            key: TlvKey::new(tlv_type as u8, prefix),
+            fields: fields(table)?,
+        })
+    }
+
+    /// A parser of the value after the prefix, into the data of its single
+    /// field or a record of its fields
+    pub(crate) fn parser(&self) -> Box<dyn Parser> {
+        match self.fields.as_slice() {
+            [field] => field.parser(),
+            fields => record(fields),
+        }
+    }
+}
+
+/// A text field which should only hold `allowed` characters
+#[derive(Debug, Clone)]
+struct CharsetCheck {
+    key: TlvKey,
+    /// The names leading to the field in records, empty for the whole value
+    path: Vec<String>,
+    allowed: String,
+}
+
+/// Linter of the charsets of a schema, issuing W1 for invalid characters
+struct Charsets {
+    checks: Vec<CharsetCheck>,
+}
+
+/// The texts at `path` in (nested) records, for every item of lists
+fn texts<'d>(data: &'d ParseData, path: &[String], found: &mut Vec<&'d str>) {
+    match (data, path.split_first()) {
+        (ParseData::List(items), _) => {
+            for item in items {
+                texts(item, path, found);
+            }
+        }
+        (ParseData::Text(text), None) => found.push(text),
+        (ParseData::Record(fields), Some((name, rest))) => {
+            for (_, data) in fields.iter().filter(|(field, _)| field == name) {
+                texts(data, rest, found);
+            }
+        }
+        _ => (),
+    }
+}
+
+impl Linter for Charsets {
+    fn lint(&self, context: &LintContext) -> Vec<LintEntry> {
+        context
+            .info()
+            .flat_map(|(key, data)| {
+                self.checks
+                    .iter()
+                    .filter(move |check| check.key == *key)
+                    .filter(move |check| {
+                        let mut found = vec![];
+                        texts(data, &check.path, &mut found);
+                        found
+                            .iter()
+                            .any(|text| text.chars().any(|c| !check.allowed.contains(c)))
+                    })
+                    .map(move |check| {
+                        let entry = LintEntry::new(Lint::Warning(1)).with_tlv(key.clone());
+                        if check.path.is_empty() {
+                            entry
+                        } else {
+                            entry.with_extra_info(format!("field {}", check.path.join(".")))
+                        }
+                    })
+            })
+            .collect()
+    }
+}
+
+/// Tlv layouts loaded at runtime, e.g. of proprietary vendor extensions.
+///
+/// A schema is a TOML file with a `[[tlv]]` table per layout: its `name`,
+/// tlv `type` (127 if omitted), the hexadecimal `prefix` of the value which
+/// identifies it & its `fields`. Every field has a `name` and a `parser`:
+///
+/// | parser | |
+/// |---|---|
+/// | `sized-text` | text after its size, up to `max` (255 if omitted) |
+/// | `exact-text` | text after its size, which must be `size` |
+/// | `sized-number` | number after its size, up to `size` bytes |
+/// | `percentage` | a percentage after its size (1) |
+/// | `mac-list` | MAC addresses after their count |
+/// | `composite` | nested `fields` |
+/// | `repeat` | `count` times (until no data is left if omitted) the `item` field |
+/// | `optional` | the `item` field, if any data is left |
+/// | `length-prefixed` | the `item` field after its length, a number of `size` bytes (1 if omitted) |
+/// | `choice` | one of the `options` fields, picked by the `tag` byte in front of it |
+///
+/// Text fields may restrict their characters to a `charset` (e.g. `A-Z0-9_`),
+/// other characters are reported as W1. A tlv with a single field is parsed
+/// into the data of that field, otherwise into a [ParseData::Record] of its
+/// fields, as is a composite field. Repeated items are parsed into a
+/// [ParseData::List], options into a record of their name & data. These
+/// map to the parsers of [crate::combinators].
+///
+/// ```toml
+/// [[tlv]]
+/// name = "vendor-status"
+/// # OUI 0a1b2c, subtype 1
+/// prefix = "0a1b2c01"
+/// fields = [
+///     { name = "state", parser = "sized-text", max = 15, charset = "A-Z" },
+///     { name = "load", parser = "percentage" },
+/// ]
+/// ```
+///
+/// # Examples
+///
+/// ```
+/// use rust_htip::schema::Schema;
+/// use rust_htip::{Dispatcher, ParseData};
+///
+/// let schema = Schema::from_toml(
+///     "[[tlv]]\n\
+///     name = \"vendor-note\"\n\
+///     prefix = \"0a1b2c01\"\n\
+///     fields = [{ name = \"note\", parser = \"sized-text\", max = 15 }]",
+/// )
+/// .unwrap();
+/// let mut dispatcher = Dispatcher::new();
+/// dispatcher.add_schema(schema);
+/// let frame = b"\x02\x07\x04ABCDEF\x04\x07\x03ABCDEF\x06\x02\x00\x78\
+///     \xfe\x07\x0a\x1b\x2c\x01\x02OK\x00\x00";
+/// let info = dispatcher.parse(frame).ok().unwrap();
+/// assert_eq!(info.info[3].1, ParseData::Text("OK".to_string()));
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Schema {
+    tlvs: Vec<TlvDefinition>,
+}
+
+impl Schema {
+    /// A schema without tlvs
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_toml(toml: &str) -> Result<Self, SchemaError> {
+        let table = toml.parse::<Table>()?;
+        let tlvs = match table.get("tlv") {
+            None => vec![],
+            Some(Value::Array(tlvs)) => tlvs
+                .iter()
+                .map(TlvDefinition::from_value)
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(SchemaError::Invalid)?,
+            Some(_) => return Err(SchemaError::Invalid("tlv must be an array".to_string())),
+        };
+        let mut keys = HashSet::new();
+        match tlvs.iter().find(|tlv| !keys.insert(&tlv.key)) {
+            Some(tlv) => Err(SchemaError::Invalid(format!(
+                "tlv {}: key defined twice",
+                tlv.name
+            ))),
+            None => Ok(Schema { tlvs }),
+        }
+    }
+
+    /// Load a TOML schema from `path`
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, SchemaError> {
+        Schema::from_toml(&fs::read_to_string(path)?)
+    }
+
+    /// A schema of `tlvs`, which must have different keys
+    pub(crate) fn from_tlvs(tlvs: Vec<TlvDefinition>) -> Self {
+        Schema { tlvs }
+    }
+
+    pub fn tlvs(&self) -> &[TlvDefinition] {
+        &self.tlvs
+    }
+
+    /// The linter of the charsets of the schema, if any
+    pub(crate) fn linter(&self) -> Option<Box<dyn Linter>> {
+        let mut checks = vec![];
+        for tlv in &self.tlvs {
+            match tlv.fields.as_slice() {
+                [field] => field.charsets(vec![], &tlv.key, &mut checks),
+                fields => {
+                    for field in fields {
+                        field.charsets(vec![field.name.clone()], &tlv.key, &mut checks);
+                    }
+                }
+            }
+        }
+        if checks.is_empty() {
+            None
+        } else {
+            Some(Box::new(Charsets { checks }))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Dispatcher;
+    use macaddr::MacAddr6;
+
+    const SCHEMA: &str = r#"
+        [[tlv]]
+        name = "vendor-status"
+        prefix = "0a:1b:2c:01"
+        fields = [
+            { name = "state", parser = "sized-text", max = 15, charset = "A-Z_" },
+            { name = "load", parser = "percentage" },
+            { name = "port", parser = "composite", fields = [
+                { name = "id", parser = "sized-number", size = 2 },
+                { name = "peers", parser = "mac-list" },
+                { name = "label", parser = "exact-text", size = 2, charset = "0-9" },
+            ] },
+        ]
+
+        # a longer model name
+        [[tlv]]
+        name = "model-name"
+        prefix = "e0271a0103"
+        fields = [{ name = "model", parser = "sized-text", max = 63 }]
+    "#;
+
+    const MANDATORY: &[u8] = b"\x02\x07\x04ABCDEF\x04\x07\x03ABCDEF\x06\x02\x00\x78";
+
+    //the data of `tlvs`, between the mandatory tlvs & the End TLV
+    fn parse(dsp: &mut Dispatcher, tlvs: &[u8]) -> (Vec<ParseData>, Vec<LintEntry>) {
+        let frame = [MANDATORY, tlvs, b"\x00\x00"].concat();
+        let info = dsp.parse(&frame).ok().unwrap();
+        assert!(info.errors.is_empty(), "{:?}", info.errors);
+        let end = info.info.len() - 1;
+        let data = info.info[3..end]
+            .iter()
+            .map(|(_, data)| data.clone())
+            .collect();
+        (data, info.lints)
+    }
+
+    fn status(state: &[u8], label: &[u8]) -> Vec<u8> {
+        let value = [
+            &b"\x0a\x1b\x2c\x01"[..],
+            &[state.len() as u8],
+            state,
+            b"\x01\x32\x02\x01\x02\x01\x00\x11\x22\x33\x44\x55",
+            &[label.len() as u8],
+            label,
+        ]
+        .concat();
+        [&[0xfe, value.len() as u8][..], &value].concat()
+    }
+
+    fn record(fields: &[(&str, ParseData)]) -> ParseData {
+        ParseData::Record(
+            fields
+                .iter()
+                .map(|(name, data)| (name.to_string(), data.clone()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn tlvs_are_parsed_into_records() {
+        let mut dsp = Dispatcher::new();
+        dsp.add_schema(Schema::from_toml(SCHEMA).unwrap());
+        let (data, lints) = parse(&mut dsp, &status(b"IDLE", b"01"));
+        assert_eq!(
+            data,
+            vec![record(&[
+                ("state", ParseData::Text("IDLE".to_string())),
+                ("load", ParseData::U32(50)),
+                (
+                    "port",
+                    record(&[
+                        ("id", ParseData::U32(0x102)),
+                        (
+                            "peers",
+                            ParseData::Mac(vec![MacAddr6::new(0, 0x11, 0x22, 0x33, 0x44, 0x55)])
+                        ),
+                        ("label", ParseData::Text("01".to_string())),
+                    ])
+                ),
+            ])]
+        );
+        assert!(lints.is_empty(), "{:?}", lints);
+    }
+
+    #[test]
+    fn schemas_replace_builtin_parsers() {
+        let model = [&b"\xfe\x26\xe0\x27\x1a\x01\x03\x20"[..], &[b'M'; 32]].concat();
+        let mut dsp = Dispatcher::new();
+        dsp.add_schema(Schema::from_toml(SCHEMA).unwrap());
+        let (data, _) = parse(&mut dsp, &model);
+        assert_eq!(data, vec![ParseData::Text("M".repeat(32))]);
+    }
+
+    #[test]
+    fn charsets_are_linted() {
+        let mut dsp = Dispatcher::new();
+        dsp.add_schema(Schema::from_toml(SCHEMA).unwrap());
+        let (_, lints) = parse(&mut dsp, &status(b"idle", b"0A"));
+        let fields = lints
+            .iter()
+            .filter(|entry| entry.lint == Lint::Warning(1))
+            .filter_map(|entry| entry.extra_info.as_deref())
+            .collect::<Vec<_>>();
+        assert_eq!(fields, vec!["field state", "field port.label"]);
+    }
+
+    #[test]
+    fn invalid_schemas_are_rejected() {
+        let invalid = |toml: &str| match Schema::from_toml(toml) {
+            Err(SchemaError::Invalid(reason)) => reason,
+            other => panic!("{:?}", other),
+        };
+        assert_eq!(
+            invalid("[[tlv]]\nname = \"a\"\nfields = [{ name = \"b\", parser = \"float\" }]"),
+            "tlv a: field b: unknown parser float"
+        );
+        assert_eq!(
+            invalid("[[tlv]]\nname = \"a\"\nprefix = \"e02\"\nfields = [{ name = \"b\", parser = \"percentage\" }]"),
+            "tlv a: odd number of digits in prefix e02"
+        );
+        assert_eq!(
+            invalid("[[tlv]]\nname = \"a\"\nfields = [{ name = \"b\", parser = \"percentage\", charset = \"0-9\" }]"),
+            "tlv a: field b: charset of a non-text field"
+        );
+        assert_eq!(
+            invalid("[[tlv]]\nname = \"a\"\nfields = [{ name = \"b\", parser = \"sized-number\", size = 9 }]"),
+            "tlv a: field b: size must be a number from 1 to 8"
+        );
+        assert!(Schema::from_toml("[[tlv]\n").is_err());
+        assert_eq!(expand("a-c_-").unwrap(), "abc_-");
+    }
+
+    #[test]
+    fn combinators_are_described() {
+        let schema = r#"
+            [[tlv]]
+            name = "ports"
+            prefix = "0a1b2c02"
+
+            [[tlv.fields]]
+            name = "state"
+            parser = "choice"
+            options = [
+                { tag = 1, name = "text", parser = "sized-text", charset = "A-Z" },
+                { tag = 2, name = "load", parser = "percentage" },
+            ]
+
+            [[tlv.fields]]
+            name = "ports"
+            parser = "repeat"
+            item.parser = "length-prefixed"
+            item.item.parser = "composite"
+            item.item.fields = [
+                { name = "id", parser = "sized-number", size = 1 },
+                { name = "label", parser = "optional", item = { parser = "sized-text", charset = "a-z" } },
+            ]
+        "#;
+        let mut dsp = Dispatcher::new();
+        dsp.add_schema(Schema::from_toml(schema).unwrap());
+        let tlv = b"\xfe\x11\x0a\x1b\x2c\x02\x01\x02OK\x02\x01\x07\x05\x01\x08\x02Ab";
+        let (data, lints) = parse(&mut dsp, tlv);
+        let port = |id, label| record(&[("id", ParseData::U32(id)), ("label", label)]);
+        assert_eq!(
+            data,
+            vec![record(&[
+                (
+                    "state",
+                    record(&[("text", ParseData::Text("OK".to_string()))])
+                ),
+                (
+                    "ports",
+                    ParseData::List(vec![
+                        port(7, ParseData::Null),
+                        port(8, ParseData::Text("Ab".to_string()))
+                    ])
+                ),
+            ])]
+        );
+        let fields = lints
+            .iter()
+            .filter_map(|entry| entry.extra_info.as_deref())
+            .collect::<Vec<_>>();
+        assert_eq!(fields, vec!["field ports.label"]);
+
+        //errors name the part that failed
+        let frame = [
+            MANDATORY,
+            b"\xfe\x0d\x0a\x1b\x2c\x02\x02\x01\x32\x02\x01\x07\x05\x01\x08",
+        ]
+        .concat();
+        let info = dsp.parse(&frame).ok().unwrap();
+        assert_eq!(
+            info.errors[0].1.to_string(),
+            "ports: item 1: Not enough data"
+        );
+    }
+}
@@ -0,0 +1,435 @@
+use crate::dispatcher::TTC_OUI;
+use crate::parsers::PerPortInfo;
+use crate::{Dispatcher, ParseData, TlvKey, TlvType, TLV};
+use macaddr::MacAddr6;
+use proptest::arbitrary::Arbitrary;
+use proptest::collection::vec;
+use proptest::prelude::*;
+
+/// What the parser of a key expects after the key prefix
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Payload {
+    /// Nothing (End TLV)
+    Null,
+    /// A subtype byte, then data
+    Typed,
+    /// A big endian number of exactly this size
+    Number(usize),
+    /// Text up to this size, without a size byte
+    Text(usize),
+    /// A size byte, then text up to this size
+    SizedText(usize),
+    /// A size byte, then text of exactly this size
+    ExactText(usize),
+    /// A size byte of 1, then a value up to 100
+    Percentage,
+    /// A size byte, then a big endian number of exactly this size
+    SizedNumber(usize),
+    /// Interface & port sized numbers, then a MAC list
+    Connections,
+    /// A MAC count, then the MACs
+    Mac,
+}
+
+impl Payload {
+    /// The payload of the parser `Dispatcher::new()` registers for `key`,
+    /// kept in line with it by `payloads_match_the_registered_parsers`
+    fn of(key: &TlvKey) -> Option<Payload> {
+        let payload = match (key.tlv_type, &key.prefix[..]) {
+            (0, []) => Payload::Null,
+            (1, []) | (2, []) => Payload::Typed,
+            (3, []) => Payload::Number(2),
+            (4, []) => Payload::Text(255),
+            (127, prefix) if prefix.starts_with(TTC_OUI) => match &prefix[TTC_OUI.len()..] {
+                [1, 1] => Payload::SizedText(255),
+                [1, 2] => Payload::ExactText(6),
+                [1, 3] | [1, 4] => Payload::SizedText(31),
+                [1, 20..=22] | [1, 51..=54] => Payload::Percentage,
+                [1, 23] => Payload::SizedNumber(6),
+                [1, 24..=27] => Payload::SizedNumber(1),
+                [1, 50] => Payload::SizedText(63),
+                [1, 80] => Payload::SizedNumber(2),
+                [2] => Payload::Connections,
+                [3] => Payload::Mac,
+                _ => return None,
+            },
+            _ => return None,
+        };
+        Some(payload)
+    }
+
+    /// Valid bytes & the data they parse into
+    fn valid(self) -> BoxedStrategy<(Vec<u8>, ParseData)> {
+        match self {
+            Payload::Null => Just((vec![], ParseData::Null)).boxed(),
+            Payload::Typed => (any::<u8>(), vec(any::<u8>(), 1..=255))
+                .prop_map(|(subtype, data)| {
+                    let bytes = [&[subtype][..], &data].concat();
+                    (bytes, ParseData::TypedData(subtype, data))
+                })
+                .boxed(),
+            Payload::Number(size) => number(size)
+                .prop_map(|(bytes, value)| (bytes, ParseData::U64(value)))
+                .boxed(),
+            Payload::Text(max) => text(1, max)
+                .prop_map(|text| (text.clone().into_bytes(), ParseData::Text(text)))
+                .boxed(),
+            Payload::SizedText(max) => sized_text(0, max),
+            Payload::ExactText(size) => sized_text(size, size),
+            Payload::Percentage => (0u8..=100)
+                .prop_map(|value| (vec![1, value], ParseData::U32(value as u32)))
+                .boxed(),
+            Payload::SizedNumber(size) => number(size)
+                .prop_map(move |(bytes, value)| {
+                    let data = if size <= 4 {
+                        ParseData::U32(value as u32)
+                    } else {
+                        ParseData::U64(value)
+                    };
+                    ([&[size as u8][..], &bytes].concat(), data)
+                })
+                .boxed(),
+            Payload::Connections => (any::<u32>(), any::<u32>(), macs())
+                .prop_map(|(interface, port, (mac_bytes, macs))| {
+                    let mut bytes = vec![4];
+                    bytes.extend(&interface.to_be_bytes());
+                    bytes.push(4);
+                    bytes.extend(&port.to_be_bytes());
+                    bytes.extend(mac_bytes);
+                    let info = PerPortInfo {
+                        interface,
+                        port,
+                        macs,
+                    };
+                    (bytes, ParseData::Connections(info))
+                })
+                .boxed(),
+            Payload::Mac => macs()
+                .prop_map(|(bytes, macs)| (bytes, ParseData::Mac(macs)))
+                .boxed(),
+        }
+    }
+}
+
+/// A big endian number of `size` bytes & its value
+fn number(size: usize) -> impl Strategy<Value = (Vec<u8>, u64)> {
+    vec(any::<u8>(), size).prop_map(|bytes| {
+        let value = bytes
+            .iter()
+            .fold(0u64, |value, byte| (value << 8) + *byte as u64);
+        (bytes, value)
+    })
+}
+
+/// Printable ascii text
+fn text(min: usize, max: usize) -> impl Strategy<Value = String> {
+    vec(0x20u8..0x7f, min..=max).prop_map(|bytes| bytes.into_iter().map(char::from).collect())
+}
+
+fn sized_text(min: usize, max: usize) -> BoxedStrategy<(Vec<u8>, ParseData)> {
+    text(min, max)
+        .prop_map(|text| {
+            let bytes = [&[text.len() as u8][..], text.as_bytes()].concat();
+            (bytes, ParseData::Text(text))
+        })
+        .boxed()
+}
+
+/// A counted MAC list & its MACs
+fn macs() -> impl Strategy<Value = (Vec<u8>, Vec<MacAddr6>)> {
+    vec(any::<[u8; 6]>(), 0..=8).prop_map(|macs| {
+        let mut bytes = vec![macs.len() as u8];
+        bytes.extend(macs.iter().flatten());
+        (bytes, macs.into_iter().map(MacAddr6::from).collect())
+    })
+}
+
+/// A generated tlv, along with the data it should parse into
+#[derive(Debug, Clone)]
+pub struct ArbitraryTlv {
+    pub tlv_type: TlvType,
+    /// The whole value, including the key prefix
+    pub value: Vec<u8>,
+    /// The data of a valid value, None for arbitrary values
+    pub expected: Option<ParseData>,
+}
+
+impl ArbitraryTlv {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        TLV::new(self.tlv_type, self.value.len(), &self.value)
+            .to_bytes()
+            .expect("generated values fit a tlv")
+    }
+}
+
+impl Arbitrary for ArbitraryTlv {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+        tlv()
+    }
+}
+
+fn registered_keys() -> Vec<TlvKey> {
+    Dispatcher::new().parser_keys().to_vec()
+}
+
+/// A tlv of a registered parser key, with a valid value
+pub fn valid_tlv() -> BoxedStrategy<ArbitraryTlv> {
+    let keys = registered_keys()
+        .into_iter()
+        .filter_map(|key| Payload::of(&key).map(|payload| (key, payload)))
+        .collect::<Vec<_>>();
+    proptest::sample::select(keys)
+        .prop_flat_map(|(key, payload)| {
+            payload.valid().prop_map(move |(bytes, data)| ArbitraryTlv {
+                tlv_type: TlvType::from(key.tlv_type),
+                value: [&key.prefix[..], &bytes].concat(),
+                expected: Some(data),
+            })
+        })
+        .boxed()
+}
+
+/// A tlv of a registered parser key, with arbitrary (mostly invalid) data
+/// after the key prefix
+pub fn invalid_tlv() -> BoxedStrategy<ArbitraryTlv> {
+    (
+        proptest::sample::select(registered_keys()),
+        vec(any::<u8>(), 0..64),
+    )
+        .prop_map(|(key, bytes)| ArbitraryTlv {
+            tlv_type: TlvType::from(key.tlv_type),
+            value: [&key.prefix[..], &bytes].concat(),
+            expected: None,
+        })
+        .boxed()
+}
+
+/// A tlv of a reserved type, with arbitrary data
+pub fn reserved_tlv() -> BoxedStrategy<ArbitraryTlv> {
+    (9u8..=126, vec(any::<u8>(), 0..64))
+        .prop_map(|(tlv_type, value)| ArbitraryTlv {
+            tlv_type: TlvType::from(tlv_type),
+            value,
+            expected: None,
+        })
+        .boxed()
+}
+
+/// Any of [valid_tlv()], [invalid_tlv()] & [reserved_tlv()]
+pub fn tlv() -> BoxedStrategy<ArbitraryTlv> {
+    prop_oneof![3 => valid_tlv(), 1 => invalid_tlv(), 1 => reserved_tlv()].boxed()
+}
+
+/// Up to `max` tlvs, from `tlvs`, followed by an End TLV
+pub fn frame(tlvs: BoxedStrategy<ArbitraryTlv>, max: usize) -> BoxedStrategy<Vec<ArbitraryTlv>> {
+    vec(tlvs, 0..=max)
+        .prop_map(|mut tlvs| {
+            //an End TLV right before the last one is followed by zero padding
+            while tlvs
+                .last()
+                .filter(|tlv| tlv.tlv_type == TlvType::End)
+                .is_some()
+            {
+                tlvs.pop();
+            }
+            tlvs.push(ArbitraryTlv {
+                tlv_type: TlvType::End,
+                value: vec![],
+                expected: Some(ParseData::Null),
+            });
+            tlvs
+        })
+        .boxed()
+}
+
+/// The bytes of a frame
+pub fn encode(tlvs: &[ArbitraryTlv]) -> Vec<u8> {
+    tlvs.iter().flat_map(ArbitraryTlv::to_bytes).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dispatcher::parse_frame;
+
+    /// Whether the payload declares its own size, so that bytes after it
+    /// are left to the caller. The others take up the whole value.
+    fn is_delimited(payload: Payload) -> bool {
+        !matches!(
+            payload,
+            Payload::Null | Payload::Typed | Payload::Number(_) | Payload::Text(_)
+        )
+    }
+
+    #[test]
+    fn every_registered_key_has_a_payload() {
+        for key in registered_keys() {
+            assert!(Payload::of(&key).is_some(), "{:?}", key);
+        }
+    }
+
+    /// Values at the limits of a payload, along with the data they parse
+    /// into, None if they must not parse fully
+    fn edges(payload: Payload) -> Vec<(Vec<u8>, Option<ParseData>)> {
+        let text = |size: usize| "a".repeat(size);
+        let sized_text = |size: usize| [&[size as u8][..], text(size).as_bytes()].concat();
+        match payload {
+            Payload::Null => vec![(vec![], Some(ParseData::Null)), (vec![0], None)],
+            Payload::Typed => vec![
+                (vec![1, 0], Some(ParseData::TypedData(1, vec![0]))),
+                (vec![1], None),
+            ],
+            Payload::Number(size) => vec![
+                (vec![0; size], Some(ParseData::U64(0))),
+                (vec![0; size + 1], None),
+            ],
+            Payload::Text(max) => vec![
+                (text(max).into_bytes(), Some(ParseData::Text(text(max)))),
+                (text(max + 1).into_bytes(), None),
+                (vec![], None),
+            ],
+            Payload::SizedText(max) => {
+                let mut edges = vec![(sized_text(max), Some(ParseData::Text(text(max))))];
+                if max < 255 {
+                    edges.push((sized_text(max + 1), None));
+                }
+                edges
+            }
+            Payload::ExactText(size) => vec![
+                (sized_text(size), Some(ParseData::Text(text(size)))),
+                (sized_text(size - 1), None),
+            ],
+            Payload::Percentage => vec![
+                (vec![1, 100], Some(ParseData::U32(100))),
+                (vec![1, 101], None),
+            ],
+            Payload::SizedNumber(size) => {
+                let value = |size: usize| [&[size as u8][..], &vec![0; size]].concat();
+                let zero = if size <= 4 {
+                    ParseData::U32(0)
+                } else {
+                    ParseData::U64(0)
+                };
+                vec![(value(size), Some(zero)), (value(size + 1), None)]
+            }
+            Payload::Connections => {
+                let info = PerPortInfo {
+                    interface: 0,
+                    port: 0,
+                    macs: vec![],
+                };
+                vec![
+                    (
+                        vec![4, 0, 0, 0, 0, 4, 0, 0, 0, 0, 0],
+                        Some(ParseData::Connections(info)),
+                    ),
+                    (vec![4, 0, 0, 0, 0], None),
+                ]
+            }
+            Payload::Mac => vec![(vec![0], Some(ParseData::Mac(vec![]))), (vec![1], None)],
+        }
+    }
+
+    //the payloads are written out by hand: fail when a registered parser
+    //changes without its payload
+    #[test]
+    fn payloads_match_the_registered_parsers() {
+        let mut dsp = Dispatcher::new();
+        for key in registered_keys() {
+            let payload = Payload::of(&key).unwrap();
+            for (bytes, expected) in edges(payload) {
+                let value = [&key.prefix[..], &bytes].concat();
+                let tlv = TLV::new(TlvType::from(key.tlv_type), value.len(), &value);
+                let (_, data, rest) = dsp.parse_tlv_rest(&tlv);
+                let data = data.ok().filter(|_| rest == 0);
+                assert_eq!(data, expected, "{:?} as {:?}: {:?}", key, payload, bytes);
+            }
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn valid_tlvs_roundtrip(tlv in valid_tlv()) {
+            let mut dsp = Dispatcher::new();
+            let parsed = TLV::new(tlv.tlv_type, tlv.value.len(), &tlv.value);
+            let (_, data, rest) = dsp.parse_tlv_rest(&parsed);
+            prop_assert_eq!(data.ok(), tlv.expected);
+            prop_assert_eq!(rest, 0);
+        }
+
+        #[test]
+        fn consumed_and_remaining_bytes_make_up_the_value(
+            tlv in valid_tlv(),
+            trailing in vec(any::<u8>(), 1..16),
+        ) {
+            let mut dsp = Dispatcher::new();
+            let value = [&tlv.value[..], &trailing].concat();
+            let parsed = TLV::new(tlv.tlv_type, value.len(), &value);
+            let (key, data, rest) = dsp.parse_tlv_rest(&parsed);
+            if matches!(Payload::of(&key), Some(payload) if is_delimited(payload)) {
+                prop_assert_eq!(data.ok(), tlv.expected);
+                prop_assert_eq!(rest, trailing.len());
+            } else {
+                prop_assert!(rest <= value.len() - key.prefix.len());
+            }
+        }
+
+        #[test]
+        fn frames_roundtrip(tlvs in frame(tlv(), 8)) {
+            let frame = encode(&tlvs);
+            let parsed = match parse_frame(&frame) {
+                Ok(parsed) => parsed,
+                Err(_) => return Err(TestCaseError::fail("the frame should parse")),
+            };
+            prop_assert_eq!(parsed.len(), tlvs.len());
+            for (parsed, tlv) in parsed.iter().zip(&tlvs) {
+                prop_assert_eq!(parsed.tlv_type(), tlv.tlv_type);
+                prop_assert_eq!(parsed.value(), &tlv.value[..]);
+            }
+            let reencoded = parsed
+                .iter()
+                .flat_map(|tlv| tlv.to_bytes().unwrap())
+                .collect::<Vec<_>>();
+            prop_assert_eq!(reencoded, frame);
+        }
+
+        #[test]
+        fn valid_frames_parse_without_errors(tlvs in frame(valid_tlv(), 8)) {
+            let mut dsp = Dispatcher::new();
+            let frame = encode(&tlvs);
+            let info = match dsp.parse(&frame) {
+                Ok(info) => info,
+                Err(_) => return Err(TestCaseError::fail("the frame should parse")),
+            };
+            prop_assert!(info.errors.is_empty());
+            let data = info.info.into_iter().map(|(_, data)| Some(data));
+            let expected = tlvs.into_iter().map(|tlv| tlv.expected);
+            prop_assert!(data.eq(expected));
+        }
+
+        #[test]
+        fn parse_never_panics(tlvs in frame(tlv(), 8), cut in any::<prop::sample::Index>()) {
+            let mut dsp = Dispatcher::new();
+            let frame = encode(&tlvs);
+            //cut anywhere, breaking tlvs
+            let frame = &frame[..cut.index(frame.len() + 1)];
+            match dsp.parse(frame) {
+                Ok(_) => {}
+                Err(err) => {
+                    err.parse(&mut dsp);
+                }
+            }
+        }
+
+        #[test]
+        fn arbitrary_bytes_never_panic(bytes in vec(any::<u8>(), 0..600)) {
+            let mut dsp = Dispatcher::new();
+            if let Err(err) = dsp.parse(&bytes) {
+                err.parse(&mut dsp);
+            }
+        }
+    }
+}
@@ -0,0 +1,140 @@
+use crate::capture::{self, ETHER_HEADER_LEN, HTIP_FILTER};
+use crate::{Dispatcher, FrameInfo, PacketInfo};
+use futures_core::Stream;
+use pcap::{Active, Capture};
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::unix::AsyncFd;
+use tokio::io::Interest;
+
+/// An asynchronous [Stream] of parsed HTIP frames, read from a live capture.
+///
+/// The capture is switched to non-blocking mode and its file descriptor is
+/// registered with the tokio reactor, so no thread is blocked while waiting
+/// for frames. Frames are only read when the stream is polled; a slow consumer
+/// leaves them queued in the kernel/pcap buffer (backpressure). Dropping the
+/// stream stops the capture and closes the device.
+///
+/// Each frame is parsed by the dispatcher and handed to `handler`, which turns
+/// the borrowed [FrameInfo] into the stream's item. Frames with misconstructed
+/// TLVs are parsed as far as possible (see [InvalidFrame::parse()](crate::InvalidFrame::parse())).
+/// The capture metadata of each frame is available in [FrameInfo::packet].
+///
+/// # Examples
+///
+/// ```no_run
+/// use futures_util::StreamExt;
+/// use rust_htip::stream::FrameStream;
+///
+/// # async fn run() -> Result<(), pcap::Error> {
+/// let capture = pcap::Capture::from_device("eth0")?.open()?;
+/// let mut frames = FrameStream::new(capture, |info| info.to_string())?;
+/// while let Some(frame) = frames.next().await {
+///     println!("{}", frame?);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct FrameStream<F> {
+    capture: AsyncFd<Capture<Active>>,
+    dispatcher: Dispatcher<'static>,
+    handler: F,
+    //index of the next packet, among the packets let through the filter
+    index: usize,
+}
+
+impl<F> FrameStream<F> {
+    /// Create a new stream over `capture`, using a default [Dispatcher]
+    ///
+    /// Must be called from within a tokio runtime.
+    pub fn new<T>(capture: Capture<Active>, handler: F) -> Result<Self, pcap::Error>
+    where
+        F: FnMut(FrameInfo<'_>) -> T,
+    {
+        FrameStream::with_dispatcher(capture, Dispatcher::new(), handler)
+    }
+
+    /// Create a new stream over `capture`, parsing with the given dispatcher
+    ///
+    /// Must be called from within a tokio runtime.
+    pub fn with_dispatcher<T>(
+        mut capture: Capture<Active>,
+        dispatcher: Dispatcher<'static>,
+        handler: F,
+    ) -> Result<Self, pcap::Error>
+    where
+        F: FnMut(FrameInfo<'_>) -> T,
+    {
+        capture.filter(HTIP_FILTER)?;
+        let capture = capture.setnonblock()?;
+        //SAFETY: the capture owns its pcap handle & thus its file descriptor,
+        //which stays open until the capture is dropped along with the AsyncFd
+        let capture = unsafe { AsyncFd::register_with_interest(capture, Interest::READABLE) }
+            .map_err(|err| pcap::Error::IoError(io::Error::from(err).kind()))?;
+        Ok(FrameStream {
+            capture,
+            dispatcher,
+            handler,
+            index: 0,
+        })
+    }
+}
+
+impl<F, T> Stream for FrameStream<F>
+where
+    F: FnMut(FrameInfo<'_>) -> T + Unpin,
+{
+    type Item = Result<T, pcap::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let FrameStream {
+            capture,
+            dispatcher,
+            handler,
+            index,
+        } = self.get_mut();
+
+        loop {
+            let mut guard = match capture.poll_read_ready_mut(cx) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(Ok(guard)) => guard,
+                Poll::Ready(Err(err)) => {
+                    return Poll::Ready(Some(Err(pcap::Error::IoError(err.kind()))))
+                }
+            };
+
+            match guard.get_inner_mut().next() {
+                Ok(packet) => {
+                    let packet_info = PacketInfo::new(
+                        *index,
+                        capture::timestamp(
+                            packet.header.ts.tv_sec as u64,
+                            packet.header.ts.tv_usec as u64,
+                        ),
+                        packet.header.caplen,
+                        packet.header.len,
+                    );
+                    *index += 1;
+                    //strip the ethernet header, skip runt frames
+                    if let (Some(packet_info), Some(htip_frame)) = (
+                        capture::with_source(packet_info, packet.data),
+                        packet.data.get(ETHER_HEADER_LEN..),
+                    ) {
+                        let info = match dispatcher.parse(htip_frame) {
+                            Ok(info) => info,
+                            Err(bad_frame) => bad_frame.parse(dispatcher),
+                        };
+                        let mut info = info.with_packet(packet_info);
+                        dispatcher.finish(&mut info);
+                        return Poll::Ready(Some(Ok(handler(info))));
+                    }
+                }
+                //nothing left in the pcap buffer, wait for the fd to be readable again
+                Err(pcap::Error::TimeoutExpired) => guard.clear_ready(),
+                Err(pcap::Error::NoMorePackets) => return Poll::Ready(None),
+                Err(err) => return Poll::Ready(Some(Err(err))),
+            }
+        }
+    }
+}
@@ -43,6 +43,11 @@ where
         self.get(&key)
     }
 
+    /// Every key, in order
+    pub fn keys(&self) -> &[K] {
+        &self.keys
+    }
+
     pub fn get(&self, key: &K) -> Option<&V> {
         self.map.get(key)
     }
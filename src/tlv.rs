@@ -91,6 +91,21 @@ impl<'a> TLV<'a> {
     pub fn is_empty(&self) -> bool {
         self.value.is_empty()
     }
+
+    /// The tlv as it appears in a frame: a 7 bit type & 9 bit length,
+    /// followed by the value; an error if the value doesn't fit the length
+    pub fn to_bytes(&self) -> Result<Vec<u8>, String> {
+        if self.value.len() > 0x1ff {
+            return Err(format!("value of {} bytes over 511", self.value.len()));
+        }
+        let header = (u16::from(self.ttype.as_byte()) << 9) | self.value.len() as u16;
+        Ok(header
+            .to_be_bytes()
+            .iter()
+            .chain(self.value)
+            .copied()
+            .collect())
+    }
 }
 
 impl fmt::Display for TLV<'_> {
@@ -0,0 +1,404 @@
+use crate::schema::{Schema, TlvDefinition};
+use crate::{FrameInfo, ParseData, TlvKey, TlvType, TLV};
+use macaddr::MacAddr6;
+use std::convert::TryFrom;
+
+#[cfg(feature = "derive")]
+pub use rust_htip_derive::HtipTlv;
+
+/// Layout of a field
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layout {
+    /// Text after its size, up to the maximum size
+    SizedText(usize),
+    /// Text after its size, which must be exactly the size
+    ExactText(usize),
+    /// Number after its size, up to the maximum size in bytes
+    SizedNumber(usize),
+    Percentage,
+    /// MAC addresses after their count
+    MacList,
+}
+
+/// A field of a [HtipTlv] type
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FieldInfo {
+    pub name: &'static str,
+    pub layout: Layout,
+    /// The characters allowed in a text field, if restricted, where `a-z`
+    /// is a range of characters
+    pub charset: Option<&'static str>,
+}
+
+/// Rust types of field values
+pub trait FieldValue: Sized {
+    /// The value of parsed data, if of the right kind & range
+    fn from_data(data: ParseData) -> Option<Self>;
+    fn to_data(&self) -> ParseData;
+}
+
+impl FieldValue for u8 {
+    fn from_data(data: ParseData) -> Option<Self> {
+        u8::try_from(data.into_u32()?).ok()
+    }
+
+    fn to_data(&self) -> ParseData {
+        ParseData::U32(u32::from(*self))
+    }
+}
+
+impl FieldValue for u16 {
+    fn from_data(data: ParseData) -> Option<Self> {
+        u16::try_from(data.into_u32()?).ok()
+    }
+
+    fn to_data(&self) -> ParseData {
+        ParseData::U32(u32::from(*self))
+    }
+}
+
+impl FieldValue for u32 {
+    fn from_data(data: ParseData) -> Option<Self> {
+        data.into_u32()
+    }
+
+    fn to_data(&self) -> ParseData {
+        ParseData::U32(*self)
+    }
+}
+
+impl FieldValue for u64 {
+    fn from_data(data: ParseData) -> Option<Self> {
+        match data {
+            ParseData::U32(value) => Some(u64::from(value)),
+            data => data.into_u64(),
+        }
+    }
+
+    fn to_data(&self) -> ParseData {
+        ParseData::U64(*self)
+    }
+}
+
+impl FieldValue for String {
+    fn from_data(data: ParseData) -> Option<Self> {
+        data.into_string()
+    }
+
+    fn to_data(&self) -> ParseData {
+        ParseData::Text(self.clone())
+    }
+}
+
+impl FieldValue for Vec<MacAddr6> {
+    fn from_data(data: ParseData) -> Option<Self> {
+        data.into_mac()
+    }
+
+    fn to_data(&self) -> ParseData {
+        ParseData::Mac(self.clone())
+    }
+}
+
+/// The bytes of a field, or why its value doesn't fit the layout
+fn encode(layout: Layout, data: &ParseData) -> Result<Vec<u8>, String> {
+    let number = match data {
+        ParseData::U32(number) => Some(u64::from(*number)),
+        ParseData::U64(number) => Some(*number),
+        _ => None,
+    };
+    match (layout, data, number) {
+        (Layout::SizedText(max), ParseData::Text(text), _) if text.len() > max.min(255) => Err(
+            format!("text of {} bytes over {}", text.len(), max.min(255)),
+        ),
+        (Layout::ExactText(size), ParseData::Text(text), _) if text.len() != size => {
+            Err(format!("text of {} bytes instead of {}", text.len(), size))
+        }
+        (Layout::SizedText(_), ParseData::Text(text), _)
+        | (Layout::ExactText(_), ParseData::Text(text), _) => {
+            Ok([&[text.len() as u8][..], text.as_bytes()].concat())
+        }
+        (Layout::SizedNumber(size), _, Some(number)) if size < 8 && number >> (8 * size) != 0 => {
+            Err(format!("{} over {} bytes", number, size))
+        }
+        (Layout::SizedNumber(size), _, Some(number)) if (1..=8).contains(&size) => {
+            Ok([&[size as u8][..], &number.to_be_bytes()[8 - size..]].concat())
+        }
+        (Layout::Percentage, _, Some(number)) if number > 100 => {
+            Err(format!("percentage of {}", number))
+        }
+        (Layout::Percentage, _, Some(number)) => Ok(vec![1, number as u8]),
+        (Layout::MacList, ParseData::Mac(macs), _) if macs.len() > 255 => {
+            Err(format!("{} MAC addresses over 255", macs.len()))
+        }
+        (Layout::MacList, ParseData::Mac(macs), _) => {
+            Ok(macs.iter().fold(vec![macs.len() as u8], |mut bytes, mac| {
+                bytes.extend(mac.as_bytes());
+                bytes
+            }))
+        }
+        (layout, data, _) => Err(format!("{:?} for a {:?} field", data, layout)),
+    }
+}
+
+/// A tlv laid out as a Rust struct, usually derived with `#[derive(HtipTlv)]`
+/// of the rust-htip-derive crate (re-exported here with the `derive`
+/// feature):
+///
+/// ```ignore
+/// #[derive(HtipTlv)]
+/// #[htip(key = "01 14")]
+/// struct CpuUsage {
+///     #[htip(percentage)]
+///     value: u8,
+/// }
+/// ```
+///
+/// The `key` of the struct follows the TTC OUI, unless another `oui` is
+/// given, in a tlv of type 127 unless another `tlv_type` is given. Fields
+/// are laid out by `sized_text(max = ..)`, `exact_text(size = ..)`,
+/// `sized_number(size = ..)`, `percentage` or `mac_list`, and text fields
+/// may restrict their characters to a `charset`.
+///
+/// [Dispatcher::register()](crate::Dispatcher::register()) parses & lints
+/// the tlvs of a type, which is then read from the parsed information with
+/// [HtipTlv::find()].
+///
+/// # Examples
+///
+/// ```
+/// use rust_htip::typed::{FieldInfo, HtipTlv, Layout};
+/// use rust_htip::{Dispatcher, ParseData};
+///
+/// //what #[derive(HtipTlv)] implements
+/// #[derive(Debug, PartialEq)]
+/// struct CpuUsage {
+///     value: u8,
+/// }
+///
+/// impl HtipTlv for CpuUsage {
+///     const NAME: &'static str = "CpuUsage";
+///     const TLV_TYPE: u8 = 127;
+///     const PREFIX: &'static [u8] = b"\xe0\x27\x1a\x01\x14";
+///     const FIELDS: &'static [FieldInfo] = &[FieldInfo {
+///         name: "value",
+///         layout: Layout::Percentage,
+///         charset: None,
+///     }];
+///
+///     fn from_values(values: Vec<ParseData>) -> Option<Self> {
+///         let mut values = values.into_iter();
+///         Some(CpuUsage {
+///             value: rust_htip::typed::FieldValue::from_data(values.next()?)?,
+///         })
+///     }
+///
+///     fn to_values(&self) -> Vec<ParseData> {
+///         vec![rust_htip::typed::FieldValue::to_data(&self.value)]
+///     }
+/// }
+///
+/// let mut dispatcher = Dispatcher::new();
+/// dispatcher.register::<CpuUsage>();
+/// let frame = [
+///     &b"\x02\x07\x04ABCDEF\x04\x07\x03ABCDEF\x06\x02\x00\x78"[..],
+///     &CpuUsage { value: 42 }.encode().unwrap(),
+///     b"\x00\x00",
+/// ]
+/// .concat();
+/// let info = dispatcher.parse(&frame).ok().unwrap();
+/// assert_eq!(CpuUsage::find(&info), Some(CpuUsage { value: 42 }));
+/// ```
+pub trait HtipTlv: Sized {
+    const NAME: &'static str;
+    const TLV_TYPE: u8;
+    /// The value prefix identifying the tlv, e.g. OUI, subtype & info ID
+    const PREFIX: &'static [u8];
+    const FIELDS: &'static [FieldInfo];
+
+    /// The value from the data of every field, in order
+    fn from_values(values: Vec<ParseData>) -> Option<Self>;
+
+    /// The data of every field, in order
+    fn to_values(&self) -> Vec<ParseData>;
+
+    fn key() -> TlvKey {
+        TlvKey::new(Self::TLV_TYPE, Self::PREFIX.to_vec())
+    }
+
+    /// The value from the data parsed by the schema of the type
+    fn from_data(data: &ParseData) -> Option<Self> {
+        let values = match (Self::FIELDS, data) {
+            ([_], data) => vec![data.clone()],
+            (_, ParseData::Record(fields)) => fields.iter().map(|(_, data)| data.clone()).collect(),
+            _ => return None,
+        };
+        Self::from_values(values)
+    }
+
+    /// The value of the first tlv of the type among the parsed information
+    fn find(info: &FrameInfo) -> Option<Self> {
+        let key = Self::key();
+        info.info
+            .iter()
+            .filter(|(entry_key, _)| *entry_key == key)
+            .find_map(|(_, data)| Self::from_data(data))
+    }
+
+    /// The tlv of the value, header included; an error if a field doesn't
+    /// fit its layout, or the value doesn't fit the 511 bytes of a tlv
+    fn encode(&self) -> Result<Vec<u8>, String> {
+        let mut value = Self::PREFIX.to_vec();
+        for (field, data) in Self::FIELDS.iter().zip(self.to_values()) {
+            let bytes = encode(field.layout, &data)
+                .map_err(|err| format!("field {}: {}", field.name, err))?;
+            value.extend(bytes);
+        }
+        TLV::new(TlvType::from(Self::TLV_TYPE), value.len(), &value).to_bytes()
+    }
+
+    /// The schema of the type: the parser of its fields & the linter of
+    /// their charsets
+    fn schema() -> Schema {
+        Schema::from_tlvs(vec![TlvDefinition::typed(
+            Self::NAME,
+            Self::key(),
+            Self::FIELDS,
+        )])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Dispatcher, Lint};
+
+    //what #[derive(HtipTlv)] implements
+    #[derive(Debug, PartialEq)]
+    struct VendorStatus {
+        state: String,
+        load: u8,
+        uptime: u64,
+        peers: Vec<MacAddr6>,
+    }
+
+    impl HtipTlv for VendorStatus {
+        const NAME: &'static str = "VendorStatus";
+        const TLV_TYPE: u8 = 127;
+        const PREFIX: &'static [u8] = b"\x0a\x1b\x2c\x01";
+        const FIELDS: &'static [FieldInfo] = &[
+            FieldInfo {
+                name: "state",
+                layout: Layout::SizedText(15),
+                charset: Some("A-Z"),
+            },
+            FieldInfo {
+                name: "load",
+                layout: Layout::Percentage,
+                charset: None,
+            },
+            FieldInfo {
+                name: "uptime",
+                layout: Layout::SizedNumber(6),
+                charset: None,
+            },
+            FieldInfo {
+                name: "peers",
+                layout: Layout::MacList,
+                charset: None,
+            },
+        ];
+
+        fn from_values(values: Vec<ParseData>) -> Option<Self> {
+            let mut values = values.into_iter();
+            Some(VendorStatus {
+                state: FieldValue::from_data(values.next()?)?,
+                load: FieldValue::from_data(values.next()?)?,
+                uptime: FieldValue::from_data(values.next()?)?,
+                peers: FieldValue::from_data(values.next()?)?,
+            })
+        }
+
+        fn to_values(&self) -> Vec<ParseData> {
+            vec![
+                self.state.to_data(),
+                self.load.to_data(),
+                self.uptime.to_data(),
+                self.peers.to_data(),
+            ]
+        }
+    }
+
+    const MANDATORY: &[u8] = b"\x02\x07\x04ABCDEF\x04\x07\x03ABCDEF\x06\x02\x00\x78";
+
+    fn status(state: &str) -> VendorStatus {
+        VendorStatus {
+            state: state.to_string(),
+            load: 50,
+            uptime: 3600,
+            peers: vec![MacAddr6::new(0, 0x11, 0x22, 0x33, 0x44, 0x55)],
+        }
+    }
+
+    #[test]
+    fn values_are_encoded() {
+        assert_eq!(
+            status("IDLE").encode().unwrap(),
+            b"\xfe\x19\x0a\x1b\x2c\x01\x04IDLE\x01\x32\x06\x00\x00\x00\x00\x0e\x10\
+            \x01\x00\x11\x22\x33\x44\x55"
+                .to_vec()
+        );
+    }
+
+    #[test]
+    fn registered_types_are_parsed_and_linted() {
+        let mut dsp = Dispatcher::new();
+        dsp.register::<VendorStatus>();
+        let frame = [MANDATORY, &status("IDLE").encode().unwrap(), b"\x00\x00"].concat();
+        let info = dsp.parse(&frame).ok().unwrap();
+        assert!(info.errors.is_empty());
+        assert!(info.lints.is_empty(), "{:?}", info.lints);
+        assert_eq!(VendorStatus::find(&info), Some(status("IDLE")));
+
+        let frame = [MANDATORY, &status("idle").encode().unwrap(), b"\x00\x00"].concat();
+        let info = dsp.parse(&frame).ok().unwrap();
+        assert_eq!(info.lints[0].lint, Lint::Warning(1));
+        assert_eq!(info.lints[0].extra_info.as_deref(), Some("field state"));
+    }
+
+    #[test]
+    fn values_out_of_their_layout_are_not_encoded() {
+        let mut value = status(&"A".repeat(16));
+        assert_eq!(
+            value.encode(),
+            Err("field state: text of 16 bytes over 15".to_string())
+        );
+        value = status("IDLE");
+        value.load = 101;
+        assert_eq!(
+            value.encode(),
+            Err("field load: percentage of 101".to_string())
+        );
+        value = status("IDLE");
+        value.uptime = 1 << 48;
+        assert_eq!(
+            value.encode(),
+            Err(format!("field uptime: {} over 6 bytes", 1u64 << 48))
+        );
+        value = status("IDLE");
+        value.peers = vec![MacAddr6::nil(); 256];
+        assert!(value.encode().is_err());
+        assert_eq!(
+            encode(Layout::ExactText(6), &ParseData::Text("ABC".to_string())),
+            Err("text of 3 bytes instead of 6".to_string())
+        );
+        assert!(encode(Layout::Percentage, &ParseData::Text("50".to_string())).is_err());
+    }
+
+    #[test]
+    fn field_values_check_their_range() {
+        assert_eq!(u8::from_data(ParseData::U32(300)), None);
+        assert_eq!(u64::from_data(ParseData::U32(300)), Some(300));
+        assert_eq!(String::from_data(ParseData::U32(1)), None);
+    }
+}
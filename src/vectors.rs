@@ -0,0 +1,624 @@
+use crate::dispatcher::{parse_frame, InvalidFrame, TTC_OUI};
+use crate::writer::FrameWriter;
+use crate::{Dispatcher, Lint, PacketInfo, ParsingError, TlvType, TLV};
+use std::fmt;
+use std::io;
+use std::time::Duration;
+
+/// Ethernet header of written test vectors: broadcast, from a locally
+/// administered address, lldp ethertype
+const ETHER_HEADER: &[u8] = b"\xff\xff\xff\xff\xff\xff\x02\x00\x00\x00\x00\x01\x88\xcc";
+
+/// The kind of a [ParsingError], without the data it refers to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    TooShort,
+    UnexpectedLength,
+    NotEqual,
+    InvalidPercentage,
+    InvalidText,
+    Unknown,
+    InvalidFrame,
+    Lint,
+    UnknownTag,
+}
+
+impl From<&ParsingError<'_>> for ErrorKind {
+    fn from(error: &ParsingError) -> Self {
+        match error {
+            ParsingError::TooShort => ErrorKind::TooShort,
+            ParsingError::UnexpectedLength(_) => ErrorKind::UnexpectedLength,
+            ParsingError::NotEqual(_) => ErrorKind::NotEqual,
+            ParsingError::InvalidPercentage(_) => ErrorKind::InvalidPercentage,
+            ParsingError::InvalidText(_) => ErrorKind::InvalidText,
+            ParsingError::Unknown => ErrorKind::Unknown,
+            ParsingError::InvalidFrame(_) => ErrorKind::InvalidFrame,
+            ParsingError::Lint(_) => ErrorKind::Lint,
+            ParsingError::UnknownTag(_) => ErrorKind::UnknownTag,
+            //the kind of what failed
+            ParsingError::Part(_, error) => ErrorKind::from(error.as_ref()),
+        }
+    }
+}
+
+/// What a test vector should trigger
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Expected {
+    Error(ErrorKind),
+    Lint(Lint),
+}
+
+impl fmt::Display for Expected {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expected::Error(kind) => write!(f, "error {:?}", kind),
+            Expected::Lint(lint) => write!(f, "lint {}", lint.code()),
+        }
+    }
+}
+
+/// A malformed frame, labelled with what it should trigger
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestVector {
+    /// What was changed
+    pub name: String,
+    pub frame: Vec<u8>,
+    pub expected: Expected,
+}
+
+impl fmt::Display for TestVector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: expect {}", self.name, self.expected)
+    }
+}
+
+impl TestVector {
+    /// Whether parsing the frame triggers the expected error or lint
+    pub fn check(&self, dispatcher: &mut Dispatcher) -> bool {
+        let info = match dispatcher.parse(&self.frame) {
+            Ok(info) => info,
+            Err(err) => err.parse(dispatcher),
+        };
+        match self.expected {
+            Expected::Error(kind) => info
+                .errors
+                .iter()
+                .any(|(_, error)| ErrorKind::from(error) == kind),
+            Expected::Lint(lint) => info.lints.iter().any(|entry| entry.lint == lint),
+        }
+    }
+}
+
+/// Write `vectors` as broadcast lldp packets, one second apart, commented
+/// with their labels (pcapng only), then finish the writer
+pub fn write_vectors(vectors: &[TestVector], writer: &mut dyn FrameWriter) -> io::Result<()> {
+    for (index, vector) in vectors.iter().enumerate() {
+        let data = [ETHER_HEADER, &vector.frame].concat();
+        let packet = PacketInfo::new(
+            index,
+            Duration::from_secs(index as u64),
+            data.len() as u32,
+            data.len() as u32,
+        );
+        writer.write_packet(&data, &packet, &[vector.to_string()])?;
+    }
+    writer.finish()
+}
+
+/// The value of a tlv; custom tlvs start with their prefix
+type Tlv = (TlvType, Vec<u8>);
+
+/// Generates malformed frames from a valid one, each mutation targeting a
+/// single parser or linter:
+/// * tlv lengths past the end of the frame & truncated tlv headers
+/// * wrong TTL lengths
+/// * sized texts longer than their tlv or their maximum size, wrong maker
+///   code sizes, trailing data & lower case maker codes
+/// * percentages over 100
+/// * zero sized numbers
+/// * MAC lists of HTIP subtypes 2 & 3 with more MACs than they hold
+/// * reserved tlv types & unknown HTIP subtypes
+/// * missing End TLVs & data after the End TLV
+///
+/// HTIP information missing from the frame (e.g. a percentage) is added
+/// first, so that every mutation is generated. Mutations are placed by the
+/// sizes the tlvs declare, and tlvs whose sizes don't match their length
+/// are left alone.
+///
+/// # Examples
+///
+/// ```
+/// use rust_htip::vectors::VectorGenerator;
+/// use rust_htip::Dispatcher;
+///
+/// let mut dispatcher = Dispatcher::new();
+/// let generator = VectorGenerator::new(&VectorGenerator::sample()).unwrap();
+/// for vector in generator.vectors() {
+///     assert!(vector.check(&mut dispatcher), "{}", vector);
+/// }
+/// ```
+pub struct VectorGenerator {
+    tlvs: Vec<Tlv>,
+}
+
+impl VectorGenerator {
+    /// A generator mutating `frame`, which must be a sequence of tlvs
+    pub fn new(frame: &[u8]) -> Result<Self, InvalidFrame<'_>> {
+        let tlvs = parse_frame(frame)?
+            .iter()
+            .map(|tlv| (tlv.tlv_type(), tlv.value().to_vec()))
+            .collect();
+        Ok(VectorGenerator { tlvs })
+    }
+
+    /// A valid frame: mandatory tlvs, HTIP info 1-4, 20 (a percentage) & 23
+    /// (the update interval), a MAC list (subtype 3) & End
+    pub fn sample() -> Vec<u8> {
+        let mut tlvs = vec![
+            (TlvType::ChassisID, b"\x04\x00\x11\x22\x33\x44\x55".to_vec()),
+            (TlvType::PortID, b"\x03\x00\x11\x22\x33\x44\x55".to_vec()),
+            (TlvType::TimeToLive, b"\x00\x78".to_vec()),
+            htip(b"\x01\x01\x06Sensor"),
+            htip(b"\x01\x02\x06CAFEBE"),
+            htip(b"\x01\x03\x05Model"),
+            htip(b"\x01\x04\x0212"),
+        ];
+        tlvs.push((TlvType::End, vec![]));
+        let mut generator = VectorGenerator { tlvs };
+        generator.ensure(PERCENTAGES[0]);
+        generator.ensure(SIZED_NUMBERS[0]);
+        generator.ensure(MAC_LIST);
+        encode(&generator.tlvs).expect("the sample fits its tlvs")
+    }
+
+    /// Add the htip tlv `value` before the End TLV, unless there is one
+    /// with the same key
+    fn ensure(&mut self, value: &[u8]) {
+        //subtype 1 is keyed by info ID too
+        let key = if value[0] == 1 {
+            &value[..2]
+        } else {
+            &value[..1]
+        };
+        if self.find(key).is_none() {
+            let index = self
+                .tlvs
+                .iter()
+                .position(|(tlv_type, _)| *tlv_type == TlvType::End)
+                .unwrap_or(self.tlvs.len());
+            self.tlvs.insert(index, htip(value));
+        }
+    }
+
+    /// Index of the htip tlv whose key (after the OUI) starts with `key`
+    fn find(&self, key: &[u8]) -> Option<usize> {
+        let prefix = [TTC_OUI, key].concat();
+        self.tlvs.iter().position(|(tlv_type, value)| {
+            *tlv_type == TlvType::Custom && value.starts_with(&prefix)
+        })
+    }
+
+    /// Every mutation of the frame
+    pub fn vectors(&self) -> Vec<TestVector> {
+        let mut base = VectorGenerator {
+            tlvs: self.tlvs.clone(),
+        };
+        for value in &[PERCENTAGES[0], SIZED_NUMBERS[0], MAC_LIST, CONNECTIONS] {
+            base.ensure(value);
+        }
+        let mut vectors = vec![];
+        base.frame_mutations(&mut vectors);
+        base.text_mutations(&mut vectors);
+        base.number_mutations(&mut vectors);
+        base.mac_mutations(&mut vectors);
+        base.type_mutations(&mut vectors);
+        vectors
+    }
+
+    /// The mutation of the frame, unless it grows a value past its tlv
+    fn vector<F>(&self, name: String, expected: Expected, mutate: F) -> Option<TestVector>
+    where
+        F: FnOnce(&mut Vec<Tlv>),
+    {
+        let mut tlvs = self.tlvs.clone();
+        mutate(&mut tlvs);
+        let frame = encode(&tlvs).ok()?;
+        Some(TestVector {
+            name,
+            frame,
+            expected,
+        })
+    }
+
+    fn frame_mutations(&self, vectors: &mut Vec<TestVector>) {
+        let invalid = Expected::Error(ErrorKind::InvalidFrame);
+        let frame = match encode(&self.tlvs) {
+            Ok(frame) => frame,
+            Err(_) => return,
+        };
+        let mut offset = 0;
+        for (index, (_, value)) in self.tlvs.iter().enumerate() {
+            //the 9 bit length can't always reach past the end
+            if frame.len() - offset - 2 < 0x1ff {
+                let mut frame = frame.clone();
+                frame[offset] |= 1;
+                frame[offset + 1] = 0xff;
+                vectors.push(TestVector {
+                    name: format!("tlv #{}: length past the end of the frame", index),
+                    frame,
+                    expected: invalid,
+                });
+            }
+            offset += value.len() + 2;
+        }
+        vectors.push(TestVector {
+            name: "truncated tlv header".to_string(),
+            frame: [&frame[..], b"\x08"].concat(),
+            expected: invalid,
+        });
+
+        let ttl = self
+            .tlvs
+            .iter()
+            .position(|(tlv_type, _)| *tlv_type == TlvType::TimeToLive);
+        if let Some(ttl) = ttl {
+            vectors.extend(self.vector(
+                "TTL of 1 byte".to_string(),
+                Expected::Error(ErrorKind::TooShort),
+                |tlvs| tlvs[ttl].1.truncate(1),
+            ));
+            vectors.extend(self.vector(
+                "TTL of 3 bytes".to_string(),
+                Expected::Error(ErrorKind::UnexpectedLength),
+                |tlvs| tlvs[ttl].1.push(0),
+            ));
+        }
+
+        vectors.extend(self.vector(
+            "no End TLV".to_string(),
+            Expected::Lint(Lint::Error(1)),
+            |tlvs| tlvs.retain(|(tlv_type, _)| *tlv_type != TlvType::End),
+        ));
+        vectors.extend(self.vector(
+            "tlv after the End TLV".to_string(),
+            Expected::Lint(Lint::Error(9)),
+            |tlvs| tlvs.push((TlvType::PortDescritpion, b"after".to_vec())),
+        ));
+    }
+
+    fn text_mutations(&self, vectors: &mut Vec<TestVector>) {
+        for (key, max) in SIZED_TEXTS {
+            let index = match self.find(key) {
+                Some(index) => index,
+                None => continue,
+            };
+            //tlvs whose text doesn't fit as declared are skipped
+            let start = match sized(&self.tlvs[index].1, SIZE) {
+                Some((_, start)) => start,
+                None => continue,
+            };
+            let available = self.tlvs[index].1.len() - start;
+            if available < 255 {
+                vectors.extend(self.vector(
+                    format!("info {}: text size past the end of the tlv", key[1]),
+                    Expected::Error(ErrorKind::TooShort),
+                    |tlvs| tlvs[index].1[SIZE] = available as u8 + 1,
+                ));
+            }
+            if *max < 255 {
+                vectors.extend(self.vector(
+                    format!("info {}: text size over {}", key[1], max),
+                    Expected::Error(ErrorKind::UnexpectedLength),
+                    |tlvs| tlvs[index].1[SIZE] = *max as u8 + 1,
+                ));
+            }
+        }
+        if let Some(index) = self.find(b"\x01\x03") {
+            vectors.extend(self.vector(
+                "info 3: trailing data".to_string(),
+                Expected::Lint(Lint::Warning(2)),
+                |tlvs| tlvs[index].1.extend(b"xx"),
+            ));
+        }
+        if let Some(index) = self.find(MAKER_CODE) {
+            if sized(&self.tlvs[index].1, SIZE)
+                .filter(|(size, _)| *size == 6)
+                .is_some()
+            {
+                vectors.extend(self.vector(
+                    "info 2: maker code of 5 characters".to_string(),
+                    Expected::Error(ErrorKind::UnexpectedLength),
+                    |tlvs| {
+                        let value = &mut tlvs[index].1;
+                        value[SIZE] = 5;
+                        value.truncate(SIZE + 6);
+                    },
+                ));
+            }
+            vectors.extend(self.vector(
+                "info 2: lower case maker code".to_string(),
+                Expected::Lint(Lint::Warning(1)),
+                |tlvs| tlvs[index].1 = htip(b"\x01\x02\x06cafebe").1,
+            ));
+        }
+    }
+
+    fn number_mutations(&self, vectors: &mut Vec<TestVector>) {
+        for key in PERCENTAGES.iter().map(|value| &value[..2]) {
+            let index = match self.find(key) {
+                Some(index) => index,
+                None => continue,
+            };
+            if let Some((1, start)) = sized(&self.tlvs[index].1, SIZE) {
+                vectors.extend(self.vector(
+                    format!("info {}: percentage of 101", key[1]),
+                    Expected::Error(ErrorKind::InvalidPercentage),
+                    |tlvs| tlvs[index].1[start] = 101,
+                ));
+            }
+        }
+        for key in SIZED_NUMBERS.iter().map(|value| &value[..2]) {
+            let index = match self.find(key) {
+                Some(index) => index,
+                None => continue,
+            };
+            if sized(&self.tlvs[index].1, SIZE)
+                .filter(|(size, _)| *size > 0)
+                .is_some()
+            {
+                //a zero size byte, followed by the original value
+                vectors.extend(self.vector(
+                    format!("info {}: declared size of 0", key[1]),
+                    Expected::Error(ErrorKind::TooShort),
+                    |tlvs| tlvs[index].1[SIZE] = 0,
+                ));
+            }
+        }
+    }
+
+    fn mac_mutations(&self, vectors: &mut Vec<TestVector>) {
+        //the MAC count follows the subtype
+        let count = self
+            .find(b"\x03")
+            .and_then(|index| Some((index, mac_count(&self.tlvs[index].1, 4)?)));
+        if let Some((index, count)) = count {
+            vectors.extend(self.vector(
+                "subtype 3: one MAC more than listed".to_string(),
+                Expected::Error(ErrorKind::TooShort),
+                |tlvs| tlvs[index].1[count] += 1,
+            ));
+        }
+        //the MAC count follows the sized interface & port numbers
+        let count = self.find(b"\x02").and_then(|index| {
+            let value = &self.tlvs[index].1;
+            let (size, start) = sized(value, 4)?;
+            let (size, start) = sized(value, start + size)?;
+            Some((index, mac_count(value, start + size)?))
+        });
+        if let Some((index, count)) = count {
+            vectors.extend(self.vector(
+                "subtype 2: one MAC more than listed".to_string(),
+                Expected::Error(ErrorKind::TooShort),
+                |tlvs| tlvs[index].1[count] += 1,
+            ));
+            if self.tlvs[index].1[count] > 0 {
+                vectors.extend(self.vector(
+                    "subtype 2: MAC list cut short".to_string(),
+                    Expected::Error(ErrorKind::TooShort),
+                    |tlvs| {
+                        let len = tlvs[index].1.len();
+                        tlvs[index].1.truncate(len - 1)
+                    },
+                ));
+            }
+        }
+    }
+
+    fn type_mutations(&self, vectors: &mut Vec<TestVector>) {
+        for tlv_type in &[9u8, 126] {
+            vectors.extend(self.vector(
+                format!("reserved tlv type {}", tlv_type),
+                Expected::Lint(Lint::Warning(3)),
+                |tlvs| {
+                    let end = tlvs.len().saturating_sub(1);
+                    tlvs.insert(end, (TlvType::from(*tlv_type), b"reserved".to_vec()))
+                },
+            ));
+        }
+        vectors.extend(self.vector(
+            "unknown HTIP subtype 4".to_string(),
+            Expected::Lint(Lint::Warning(3)),
+            |tlvs| {
+                let end = tlvs.len().saturating_sub(1);
+                tlvs.insert(end, htip(b"\x04unknown"))
+            },
+        ));
+    }
+}
+
+/// Offset of the size byte in values of HTIP subtype 1
+const SIZE: usize = 5;
+
+/// Keys (after the OUI) & maximum sizes of sized texts
+const SIZED_TEXTS: &[(&[u8], usize)] = &[
+    (b"\x01\x01", 255),
+    (b"\x01\x03", 31),
+    (b"\x01\x04", 31),
+    (b"\x01\x32", 63),
+];
+
+const MAKER_CODE: &[u8] = b"\x01\x02";
+
+/// Valid values of percentages
+const PERCENTAGES: &[&[u8]] = &[
+    b"\x01\x14\x01\x32",
+    b"\x01\x15\x01\x32",
+    b"\x01\x16\x01\x32",
+    b"\x01\x33\x01\x32",
+    b"\x01\x34\x01\x32",
+    b"\x01\x35\x01\x32",
+    b"\x01\x36\x01\x32",
+];
+
+/// Valid values of sized numbers
+const SIZED_NUMBERS: &[&[u8]] = &[
+    b"\x01\x17\x06\x00\x00\x00\x00\x00\x3c",
+    b"\x01\x18\x01\x01",
+    b"\x01\x19\x01\x01",
+    b"\x01\x1a\x01\x01",
+    b"\x01\x1b\x01\x01",
+    b"\x01\x50\x02\x00\x01",
+];
+
+/// One MAC address (subtype 3)
+const MAC_LIST: &[u8] = b"\x03\x01\x00\x11\x22\x33\x44\x55";
+
+/// Interface 1, port 2, one MAC address (subtype 2)
+const CONNECTIONS: &[u8] =
+    b"\x02\x04\x00\x00\x00\x01\x04\x00\x00\x00\x02\x01\x00\x11\x22\x33\x44\x66";
+
+/// A custom tlv with the TTC OUI, followed by `value`
+fn htip(value: &[u8]) -> Tlv {
+    (TlvType::Custom, [TTC_OUI, value].concat())
+}
+
+/// The declared size & the start of the sized field whose size byte is at
+/// `offset`, if the field fits in `value`
+fn sized(value: &[u8], offset: usize) -> Option<(usize, usize)> {
+    let size = usize::from(*value.get(offset)?);
+    let start = offset + 1;
+    Some((size, start)).filter(|_| start + size <= value.len())
+}
+
+/// `offset`, if it holds the count of a MAC list that ends `value` and can
+/// hold one more MAC
+fn mac_count(value: &[u8], offset: usize) -> Option<usize> {
+    let count = usize::from(*value.get(offset)?);
+    Some(offset).filter(|_| count < 255 && value.len() == offset + 1 + 6 * count)
+}
+
+/// The bytes of `tlvs`, unless a value is over the 511 bytes of a tlv
+fn encode(tlvs: &[Tlv]) -> Result<Vec<u8>, String> {
+    tlvs.iter()
+        .map(|(tlv_type, value)| TLV::new(*tlv_type, value.len(), value).to_bytes())
+        .collect::<Result<Vec<_>, _>>()
+        .map(|tlvs| tlvs.concat())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::writer::PcapngWriter;
+
+    #[test]
+    fn sample_is_valid() {
+        let mut dsp = Dispatcher::new();
+        let frame = VectorGenerator::sample();
+        let info = match dsp.parse(&frame) {
+            Ok(info) => info,
+            Err(_) => panic!("the sample should parse!"),
+        };
+        assert!(info.errors.is_empty(), "{:?}", info.errors);
+        assert!(info.lints.is_empty(), "{:?}", info.lints);
+    }
+
+    #[test]
+    fn vectors_trigger_their_label() {
+        let mut dsp = Dispatcher::new();
+        let generator = match VectorGenerator::new(&VectorGenerator::sample()) {
+            Ok(generator) => generator,
+            Err(_) => panic!("the sample should parse!"),
+        };
+        let vectors = generator.vectors();
+        assert!(vectors.len() > 30, "{}", vectors.len());
+        for vector in &vectors {
+            assert!(vector.check(&mut dsp), "{}", vector);
+        }
+    }
+
+    #[test]
+    fn missing_info_is_added() {
+        //mandatory tlvs only
+        let frame = b"\x02\x07\x04ABCDEF\x04\x07\x03ABCDEF\x06\x02\x00\x78\x00\x00";
+        let generator = match VectorGenerator::new(frame) {
+            Ok(generator) => generator,
+            Err(_) => panic!("this should parse, check frame!"),
+        };
+        let vectors = generator.vectors();
+        let names = vectors
+            .iter()
+            .map(|vector| vector.name.as_str())
+            .collect::<Vec<_>>();
+        assert!(names.contains(&"info 20: percentage of 101"));
+        assert!(names.contains(&"subtype 2: one MAC more than listed"));
+        assert!(!names.contains(&"info 3: trailing data"));
+        let mut dsp = Dispatcher::new();
+        assert!(vectors.iter().all(|vector| vector.check(&mut dsp)));
+    }
+
+    #[test]
+    fn mismatched_tlvs_are_not_mutated() {
+        //a category without size, an info 20 percentage of 2 bytes & 2 listed MACs
+        //missing from subtype 3
+        let frame = b"\x02\x07\x04ABCDEF\x04\x07\x03ABCDEF\x06\x02\x00\x78\
+            \xfe\x05\xe0\x27\x1a\x01\x01\
+            \xfe\x08\xe0\x27\x1a\x01\x14\x02\x00\x32\
+            \xfe\x05\xe0\x27\x1a\x03\x02\
+            \x00\x00";
+        let generator = match VectorGenerator::new(frame) {
+            Ok(generator) => generator,
+            Err(_) => panic!("this should parse, check frame!"),
+        };
+        let vectors = generator.vectors();
+        let names = vectors
+            .iter()
+            .map(|vector| vector.name.as_str())
+            .collect::<Vec<_>>();
+        assert!(!names.contains(&"info 1: text size past the end of the tlv"));
+        assert!(!names.contains(&"info 20: percentage of 101"));
+        assert!(!names.contains(&"subtype 3: one MAC more than listed"));
+        //added info is still mutated
+        assert!(names.contains(&"subtype 2: one MAC more than listed"));
+    }
+
+    #[test]
+    fn mutations_past_the_tlv_length_are_skipped() {
+        //a 511 byte info 3, with trailing data
+        let info = [&b"\xff\xff\xe0\x27\x1a\x01\x03\x01M"[..], &[b'x'; 504]].concat();
+        let frame = [
+            &b"\x02\x07\x04ABCDEF\x04\x07\x03ABCDEF\x06\x02\x00\x78"[..],
+            &info,
+            b"\x00\x00",
+        ]
+        .concat();
+        let generator = match VectorGenerator::new(&frame) {
+            Ok(generator) => generator,
+            Err(_) => panic!("this should parse, check frame!"),
+        };
+        let vectors = generator.vectors();
+        assert!(vectors
+            .iter()
+            .all(|vector| vector.name != "info 3: trailing data"));
+        assert!(vectors
+            .iter()
+            .any(|vector| vector.name == "info 3: text size over 31"));
+    }
+
+    #[test]
+    fn vectors_are_written_with_labels() {
+        let generator = VectorGenerator::new(&VectorGenerator::sample())
+            .ok()
+            .unwrap();
+        let vectors = generator.vectors();
+        let mut file = vec![];
+        {
+            let mut writer = PcapngWriter::new(&mut file).unwrap();
+            write_vectors(&vectors[..1], &mut writer).unwrap();
+        }
+        let label = vectors[0].to_string();
+        assert!(file
+            .windows(label.len())
+            .any(|window| window == label.as_bytes()));
+        assert!(file.windows(2).any(|window| window == b"\x88\xcc"));
+    }
+}
@@ -0,0 +1,272 @@
+use crate::pcapng::*;
+use crate::PacketInfo;
+use std::io::{self, Write};
+
+/// Link type of ethernet interfaces
+const ETHERNET: u16 = 1;
+const PCAP_MAGIC: u32 = 0xa1b2_c3d4;
+const SNAPLEN: u32 = 65535;
+/// Largest value of a pcapng option
+const MAX_OPTION: usize = 0xffff;
+
+/// Writes captured packets into a capture file
+pub trait FrameWriter {
+    /// Write a complete ethernet packet, along with its capture metadata.
+    /// Comments are kept only if the file format supports them.
+    fn write_packet(
+        &mut self,
+        data: &[u8],
+        packet: &PacketInfo,
+        comments: &[String],
+    ) -> io::Result<()>;
+
+    /// Flush the packets written so far. Errors of a buffered writer are
+    /// lost if it is only flushed when dropped.
+    fn finish(&mut self) -> io::Result<()>;
+}
+
+/// Writes a (classic) pcap file, with microsecond timestamps.
+/// Comments and interfaces are not supported by the format.
+pub struct PcapWriter<W> {
+    writer: W,
+}
+
+impl<W: Write> PcapWriter<W> {
+    /// Create a new writer, writing the file header
+    pub fn new(mut writer: W) -> io::Result<Self> {
+        writer.write_all(&PCAP_MAGIC.to_le_bytes())?;
+        //version 2.4
+        writer.write_all(&2u16.to_le_bytes())?;
+        writer.write_all(&4u16.to_le_bytes())?;
+        //timezone offset & timestamp accuracy, both always zero
+        writer.write_all(&[0u8; 8])?;
+        writer.write_all(&SNAPLEN.to_le_bytes())?;
+        writer.write_all(&u32::from(ETHERNET).to_le_bytes())?;
+        Ok(PcapWriter { writer })
+    }
+
+    /// Flush and return the underlying writer
+    pub fn into_inner(mut self) -> io::Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+impl<W: Write> FrameWriter for PcapWriter<W> {
+    fn write_packet(
+        &mut self,
+        data: &[u8],
+        packet: &PacketInfo,
+        _comments: &[String],
+    ) -> io::Result<()> {
+        self.writer
+            .write_all(&(packet.timestamp.as_secs() as u32).to_le_bytes())?;
+        self.writer
+            .write_all(&packet.timestamp.subsec_micros().to_le_bytes())?;
+        self.writer.write_all(&(data.len() as u32).to_le_bytes())?;
+        let len = packet.len.max(data.len() as u32);
+        self.writer.write_all(&len.to_le_bytes())?;
+        self.writer.write_all(data)
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Writes a pcapng file, with nanosecond timestamps. Every distinct
+/// [PacketInfo::interface] gets its own interface description, and
+/// comments are attached to their packets.
+pub struct PcapngWriter<W> {
+    writer: W,
+    interfaces: Vec<Option<String>>,
+}
+
+impl<W: Write> PcapngWriter<W> {
+    /// Create a new writer, writing the section header
+    pub fn new(writer: W) -> io::Result<Self> {
+        let mut instance = PcapngWriter {
+            writer,
+            interfaces: vec![],
+        };
+        let mut body = BYTE_ORDER_MAGIC.to_le_bytes().to_vec();
+        //version 1.0
+        body.extend(&1u16.to_le_bytes());
+        body.extend(&0u16.to_le_bytes());
+        //unspecified section length
+        body.extend(&(-1i64).to_le_bytes());
+        instance.write_block(SECTION_HEADER, &body)?;
+        Ok(instance)
+    }
+
+    /// Flush and return the underlying writer
+    pub fn into_inner(mut self) -> io::Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    fn write_block(&mut self, btype: u32, body: &[u8]) -> io::Result<()> {
+        let total = (body.len() + 12) as u32;
+        self.writer.write_all(&btype.to_le_bytes())?;
+        self.writer.write_all(&total.to_le_bytes())?;
+        self.writer.write_all(body)?;
+        self.writer.write_all(&total.to_le_bytes())
+    }
+
+    /// The id of the interface, describing it first if needed
+    fn interface_id(&mut self, interface: &Option<String>) -> io::Result<u32> {
+        if let Some(id) = self.interfaces.iter().position(|known| known == interface) {
+            return Ok(id as u32);
+        }
+
+        let mut body = ETHERNET.to_le_bytes().to_vec();
+        body.extend(&[0u8; 2]);
+        body.extend(&SNAPLEN.to_le_bytes());
+        if let Some(name) = interface {
+            push_option(&mut body, IF_NAME, option_text(name));
+        }
+        //nanosecond resolution
+        push_option(&mut body, IF_TSRESOL, &[9]);
+        push_option(&mut body, OPT_END, &[]);
+        self.write_block(INTERFACE_DESCRIPTION, &body)?;
+
+        self.interfaces.push(interface.clone());
+        Ok(self.interfaces.len() as u32 - 1)
+    }
+}
+
+impl<W: Write> FrameWriter for PcapngWriter<W> {
+    fn write_packet(
+        &mut self,
+        data: &[u8],
+        packet: &PacketInfo,
+        comments: &[String],
+    ) -> io::Result<()> {
+        let interface_id = self.interface_id(&packet.interface)?;
+        let timestamp = packet.timestamp.as_nanos() as u64;
+
+        let mut body = interface_id.to_le_bytes().to_vec();
+        body.extend(&((timestamp >> 32) as u32).to_le_bytes());
+        body.extend(&(timestamp as u32).to_le_bytes());
+        body.extend(&(data.len() as u32).to_le_bytes());
+        body.extend(&packet.len.max(data.len() as u32).to_le_bytes());
+        body.extend(data);
+        pad(&mut body);
+        if !comments.is_empty() {
+            for comment in comments {
+                push_option(&mut body, OPT_COMMENT, option_text(comment));
+            }
+            push_option(&mut body, OPT_END, &[]);
+        }
+        self.write_block(ENHANCED_PACKET, &body)
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// The bytes of `text` that fit an option, cut on a character boundary
+fn option_text(text: &str) -> &[u8] {
+    let mut end = text.len().min(MAX_OPTION);
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text.as_bytes()[..end]
+}
+
+/// Pad to 32 bits
+fn pad(body: &mut Vec<u8>) {
+    body.resize((body.len() + 3) & !3, 0);
+}
+
+fn push_option(body: &mut Vec<u8>, code: u16, value: &[u8]) {
+    body.extend(&code.to_le_bytes());
+    body.extend(&(value.len() as u16).to_le_bytes());
+    body.extend(value);
+    pad(body);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn packet(index: usize, interface: Option<&str>) -> PacketInfo {
+        let packet = PacketInfo::new(index, Duration::new(1, 5000), 4, 10);
+        match interface {
+            Some(name) => packet.with_interface(name.to_string()),
+            None => packet,
+        }
+    }
+
+    #[test]
+    fn pcap_header_and_record() {
+        let mut writer = PcapWriter::new(vec![]).unwrap();
+        writer
+            .write_packet(b"abcd", &packet(0, None), &["lost".to_string()])
+            .unwrap();
+        let file = writer.into_inner().unwrap();
+
+        assert_eq!(file.len(), 24 + 16 + 4);
+        assert_eq!(&file[..4], b"\xd4\xc3\xb2\xa1");
+        //ts 1s 5us, caplen 4, len 10
+        assert_eq!(
+            &file[24..],
+            b"\x01\x00\x00\x00\x05\x00\x00\x00\x04\x00\x00\x00\x0a\x00\x00\x00abcd"
+        );
+    }
+
+    #[test]
+    fn pcapng_roundtrip_keeps_interfaces_and_comments() {
+        let mut writer = PcapngWriter::new(vec![]).unwrap();
+        let comments = vec!["E1: No End TLV".to_string(), "W1".to_string()];
+        writer
+            .write_packet(b"abcd", &packet(0, Some("eth0")), &comments)
+            .unwrap();
+        writer
+            .write_packet(b"efghi", &packet(1, Some("eth1")), &[])
+            .unwrap();
+        writer
+            .write_packet(b"jk", &packet(2, Some("eth0")), &[])
+            .unwrap();
+        let file = writer.into_inner().unwrap();
+
+        let mut reader = PcapngReader::new(&file[..]).unwrap();
+        let packets = reader.by_ref().collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(packets.len(), 3);
+        assert_eq!(packets[0].data, b"abcd");
+        assert_eq!(packets[0].len, 10);
+        assert_eq!(packets[0].timestamp, Duration::new(1, 5000));
+        assert_eq!(packets[0].comments, comments);
+        assert_eq!(packets[1].interface_id, 1);
+        assert_eq!(packets[1].data, b"efghi");
+        assert!(packets[1].comments.is_empty());
+        assert_eq!(packets[2].interface_id, 0);
+
+        assert_eq!(reader.interfaces().len(), 2);
+        assert_eq!(reader.interface(1).unwrap().name.as_deref(), Some("eth1"));
+    }
+
+    #[test]
+    fn long_options_are_cut_on_a_character_boundary() {
+        //3 byte characters, the last one straddling the option limit
+        let long = "あ".repeat(MAX_OPTION / 3 + 1);
+        let mut writer = PcapngWriter::new(vec![]).unwrap();
+        writer
+            .write_packet(
+                b"abcd",
+                &packet(0, Some(&long)),
+                std::slice::from_ref(&long),
+            )
+            .unwrap();
+        writer.finish().unwrap();
+        let file = writer.into_inner().unwrap();
+
+        let mut reader = PcapngReader::new(&file[..]).unwrap();
+        let packet = reader.next().unwrap().unwrap();
+        let cut = "あ".repeat(MAX_OPTION / 3);
+        assert_eq!(packet.comments, vec![cut.clone()]);
+        assert_eq!(reader.interface(0).unwrap().name.as_deref(), Some(&cut[..]));
+    }
+}